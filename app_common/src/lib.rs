@@ -1,6 +1,8 @@
+pub mod options;
+pub mod protocol;
 pub mod tree_format;
 
-use std::{io::Read, net::TcpStream, num::ParseIntError, path::PathBuf};
+use std::{fmt::Display, io::{Read, Write}, net::{Shutdown, TcpStream}, num::ParseIntError, path::PathBuf};
 
 use clap::{App, Arg};
 
@@ -9,10 +11,81 @@ pub enum AppError {
     Msg(String)
 }
 
+/// How the AST is received from dcg.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transport {
+    /// Connect to dcg on this port, read the AST from the socket and write a `protocol::Response` back.
+    Tcp(u16),
+
+    /// Read the AST from stdin and write a `protocol::Response` to stdout.
+    Stdio
+}
+
+/// An open connection to dcg.
+enum Channel {
+    Tcp(TcpStream),
+    Stdio
+}
+
 #[derive(Debug)]
-struct AppConfig {
-    port: i32,
-    output_dir: PathBuf
+pub struct AppConfig {
+    transport: Transport,
+    output_dir: PathBuf,
+
+    /// Print the AST received from dcg to stderr.
+    debug_ast: bool
+}
+
+impl AppConfig {
+    pub fn output_dir(&self) -> &PathBuf {
+        &self.output_dir
+    }
+}
+
+/// A code generation backend. Built-in backends are linked into dcg directly and `run` turns any
+/// backend into an executable that dcg talks to over the generator protocol.
+pub trait CodeGenerator: Send + Sync {
+    /// The language features the generator supports. dcg refuses to run it on input using others.
+    fn features(&self) -> &[protocol::Feature];
+
+    /// Returns the files to write, relative to the output directory, and any diagnostics.
+    fn generate(&self, ast: &ast::Root, options: &options::Options) -> Result<protocol::Response, AppError>;
+}
+
+impl Channel {
+    fn open(transport: Transport) -> Result<Channel, AppError> {
+        match transport {
+            Transport::Tcp(port) => Ok(Channel::Tcp(TcpStream::connect(format!("127.0.0.1:{}", port))?)),
+            Transport::Stdio => Ok(Channel::Stdio)
+        }
+    }
+
+    fn read_to_string(&mut self, buf: &mut String) -> Result<usize, AppError> {
+        match self {
+            Channel::Tcp(stream) => Ok(stream.read_to_string(buf)?),
+            Channel::Stdio => Ok(std::io::stdin().read_to_string(buf)?)
+        }
+    }
+
+    fn write_response(&mut self, response: &protocol::Response) -> Result<(), AppError> {
+        let response = serde_json::to_string(response)?;
+        match self {
+            Channel::Tcp(stream) => {
+                stream.write_all(response.as_bytes())?;
+                stream.shutdown(Shutdown::Write)?;
+            },
+            Channel::Stdio => println!("{}", response)
+        }
+        Ok(())
+    }
+}
+
+impl Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AppError::Msg(msg) => f.write_str(msg)
+        }
+    }
 }
 
 impl From<ParseIntError> for AppError {
@@ -35,59 +108,115 @@ impl From<serde_json::Error> for AppError {
 
 impl From<&str> for AppError {
     fn from(s: &str) -> Self {
-        AppError::Msg(format!("{}", s))
+        AppError::Msg(s.to_string())
     }
 }
 
-fn process_args(args: App) -> Result<AppConfig, AppError> {
+fn process_args(args: App, capabilities: &protocol::Capabilities) -> Result<AppConfig, AppError> {
     let args = args.get_matches();
 
+    if args.is_present("capabilities") {
+        println!("{}", serde_json::to_string(capabilities)?);
+        std::process::exit(0);
+    }
+
     let port = args.value_of("port").ok_or("No port specified.")?;
     let output_dir = args.value_of("output_dir").ok_or("No output dir specified.")?;
 
-    let port = port.parse::<i32>()?;
+    let transport = match port {
+        "-" => Transport::Stdio,
+        port => Transport::Tcp(port.parse::<u16>()?)
+    };
     let output_dir = PathBuf::from(output_dir);
 
     Ok(AppConfig {
-        port,
+        transport,
         output_dir,
+        debug_ast: args.is_present("debug_ast")
     })
 }
 
-fn process_stream(config: AppConfig) -> Result<ast::Root, AppError> {
-    let mut stream = TcpStream::connect(format!("127.0.0.1:{}", config.port))?;
-
+fn process_stream(channel: &mut Channel, config: &AppConfig) -> Result<protocol::Request, AppError> {
     let mut str = String::new();
-    let nread = stream.read_to_string(&mut str)?;
-    
-    println!("Read {} bytes.", nread);
+    let nread = channel.read_to_string(&mut str)?;
 
-    let ast = serde_json::from_str::<ast::Root>(&str)?;
+    eprintln!("Read {} bytes.", nread);
+
+    // Check the version first: if it doesn't match, the rest of the request may not parse.
+    let header = serde_json::from_str::<protocol::RequestHeader>(&str)?;
+    if header.protocol_version != protocol::PROTOCOL_VERSION {
+        return Err(AppError::Msg(format!(
+            "dcg uses protocol version {} but this generator only supports version {}.",
+            header.protocol_version, protocol::PROTOCOL_VERSION
+        )));
+    }
 
-    tree_format::display_debug_ast(&ast);
+    let request = serde_json::from_str::<protocol::Request>(&str)?;
+    eprintln!("Request from dcg {}", request.dcg_version);
 
-    Ok(ast)
+    if config.debug_ast {
+        tree_format::display_debug_ast(&request.ast);
+    }
+
+    Ok(request)
 }
 
-pub fn get_ast(app_name: &str, app_desc: &str) -> Result<ast::Root, AppError> {
+/// Runs a generator executable: reads the command line, receives the AST from dcg, calls `generator`
+/// and sends the files it produced back to dcg. Exits the process with a non-zero status on failure.
+pub fn run(app_name: &str, app_desc: &str, generator: &dyn CodeGenerator) {
     let app = App::new(app_name)
         .version("0.1")
         .about(app_desc)
         .arg(
             Arg::with_name("port")
-                .help("Port to connect to ast server.")
+                .help("Port to connect to ast server, or \"-\" to read the AST from stdin.")
                 .multiple(false)
-                .required(true)
+                .required_unless("capabilities")
                 .index(1)
         )
         .arg(
             Arg::with_name("output_dir")
                 .help("Output directory.")
                 .multiple(false)
-                .required(true)
+                .required_unless("capabilities")
+        )
+        .arg(
+            Arg::with_name("capabilities")
+                .long("capabilities")
+                .help("Print the protocol version and language features supported by this generator and exit.")
+        )
+        .arg(
+            Arg::with_name("debug_ast")
+                .long("debug-ast")
+                .help("Print the AST received from dcg.")
         );
 
-    let config = process_args(app)?;
-    println!("{:#?}", config);
-    process_stream(config)
+    let config = match process_args(app, &protocol::Capabilities::new(generator.features())) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    eprintln!("{:#?}", config);
+
+    let mut channel = match Channel::open(config.transport) {
+        Ok(channel) => channel,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let response = process_stream(&mut channel, &config)
+        .and_then(|request| generator.generate(&request.ast, &options::Options::new(request.options)))
+        .unwrap_or_else(|e| protocol::Response::from_error(e.to_string()));
+
+    if let Err(e) = channel.write_response(&response) {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+    if response.has_errors() {
+        std::process::exit(1);
+    }
 }
\ No newline at end of file
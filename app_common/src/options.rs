@@ -0,0 +1,57 @@
+use std::{collections::BTreeMap, fmt::Display, str::FromStr};
+
+use crate::AppError;
+
+/// The options passed to a generator with `dcg --opt <generator>:<key>=<value>`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Options {
+    values: BTreeMap<String, String>
+}
+
+impl Options {
+    pub fn new(values: BTreeMap<String, String>) -> Options {
+        Options { values }
+    }
+
+    /// Returns an error naming the first option that isn't in `known`.
+    pub fn check(&self, known: &[&str]) -> Result<(), AppError> {
+        match self.values.keys().find(|key| !known.contains(&key.as_str())) {
+            Some(key) => Err(AppError::Msg(format!("Unknown option \"{}\". Known options: {}", key, known.join(", ")))),
+            None => Ok(())
+        }
+    }
+
+    pub fn values(&self) -> &BTreeMap<String, String> {
+        &self.values
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    /// Splits a comma-separated option, e.g. `derive=Clone,Debug`. Empty items are dropped.
+    pub fn get_list(&self, key: &str) -> Option<Vec<String>> {
+        self.get(key).map(|value| value.split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(String::from)
+            .collect())
+    }
+
+    pub fn get_parsed<T>(&self, key: &str) -> Result<Option<T>, AppError>
+    where
+        T: FromStr,
+        T::Err: Display
+    {
+        match self.get(key) {
+            Some(value) => value.parse::<T>()
+                .map(Some)
+                .map_err(|e| AppError::Msg(format!("Invalid value \"{}\" for option \"{}\": {}", value, key, e))),
+            None => Ok(None)
+        }
+    }
+
+    pub fn get_bool(&self, key: &str) -> Result<Option<bool>, AppError> {
+        self.get_parsed::<bool>(key)
+    }
+}
@@ -0,0 +1,193 @@
+use std::{collections::{BTreeMap, BTreeSet}, path::PathBuf};
+
+use ast::{Container, Field, Primitive, ResolvedType, Root, Type, TypeRef};
+use serde::{Serialize, Deserialize};
+
+/// Bumped whenever `Request`, `Response` or the shape of `ast::Root` changes incompatibly.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Sent by dcg to a generator.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Request {
+    pub protocol_version: u32,
+    pub dcg_version: String,
+
+    #[serde(default)]
+    pub options: BTreeMap<String, String>,
+
+    pub ast: Root
+}
+
+/// Just the version of a `Request`, so that it can be checked before the rest is parsed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RequestHeader {
+    pub protocol_version: u32
+}
+
+/// Language features that a generator may not support.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Feature {
+    Generics,
+    Map,
+    Set,
+    Option,
+
+    /// Default values for struct fields.
+    Defaults,
+
+    /// Struct fields written `name?: T`.
+    OptionalFields,
+
+    /// `const` declarations.
+    Constants,
+
+    /// Enums whose variants are integer discriminants, written `enum Color : UInt8 { Red = 1 }`.
+    CLikeEnums,
+
+    /// `type` declarations.
+    TypeAliases,
+
+    /// `newtype` declarations.
+    Newtypes,
+
+    /// The `Bytes` primitive.
+    Bytes,
+
+    /// `Array<T N>`.
+    Arrays,
+
+    /// A feature added to the protocol after this version of dcg.
+    #[serde(other)]
+    Unknown
+}
+
+
+/// Printed by a generator run with `--capabilities`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Capabilities {
+    pub protocol_version: u32,
+
+    #[serde(default)]
+    pub features: BTreeSet<Feature>
+}
+
+/// Sent by a generator once it is done. dcg writes `files` to the output directory itself.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Response {
+    #[serde(default)]
+    pub files: Vec<OutputFile>,
+
+    #[serde(default)]
+    pub diagnostics: Vec<Diagnostic>,
+
+    /// Set by dcg when it killed the generator for running past its timeout. Not sent by generators.
+    #[serde(skip)]
+    pub timed_out: bool
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OutputFile {
+    /// Relative to the output directory.
+    pub path: PathBuf,
+    pub contents: String
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String
+}
+
+impl Response {
+    pub fn from_files(files: Vec<OutputFile>) -> Response {
+        Response { files, diagnostics: vec![], timed_out: false }
+    }
+
+    pub fn from_error(message: String) -> Response {
+        Response { files: vec![], diagnostics: vec![Diagnostic { severity: Severity::Error, message }], timed_out: false }
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(|d| d.severity == Severity::Error)
+    }
+}
+
+impl Request {
+    pub fn new(ast: Root) -> Request {
+        Request { protocol_version: PROTOCOL_VERSION, dcg_version: String::new(), options: BTreeMap::new(), ast }
+    }
+}
+
+impl Capabilities {
+    pub fn new(features: &[Feature]) -> Capabilities {
+        Capabilities { protocol_version: PROTOCOL_VERSION, features: features.iter().copied().collect() }
+    }
+}
+
+impl Feature {
+    /// Every feature known to this version of the protocol.
+    pub const ALL: &'static [Feature] = &[Feature::Generics, Feature::Map, Feature::Set, Feature::Option, Feature::Defaults, Feature::OptionalFields, Feature::Constants, Feature::CLikeEnums, Feature::TypeAliases, Feature::Newtypes, Feature::Bytes, Feature::Arrays];
+
+    /// Returns the features that `root` makes use of.
+    pub fn used_by(root: &Root) -> BTreeSet<Feature> {
+        let mut features = BTreeSet::new();
+        if root.files().iter().any(|f| !f.consts().is_empty()) {
+            features.insert(Feature::Constants);
+        }
+        for type_def in root.files().iter().flat_map(|f| f.types()) {
+            if !type_def.params().is_empty() {
+                features.insert(Feature::Generics);
+            }
+            let fields: &[Field] = match type_def.type_() {
+                Type::Struct(s) => s.fields(),
+                Type::Enum(e) => e.fields(),
+                Type::CEnum(_) => {
+                    features.insert(Feature::CLikeEnums);
+                    &[]
+                },
+                Type::Alias(target) => {
+                    features.insert(Feature::TypeAliases);
+                    Feature::used_by_type_ref(target, &mut features);
+                    &[]
+                },
+                Type::Newtype(target) => {
+                    features.insert(Feature::Newtypes);
+                    Feature::used_by_type_ref(target, &mut features);
+                    &[]
+                },
+                Type::Primitive(_) => &[]
+            };
+            for field in fields {
+                if field.default().is_some() {
+                    features.insert(Feature::Defaults);
+                }
+                if *field.optional() {
+                    features.insert(Feature::OptionalFields);
+                }
+                Feature::used_by_type_ref(field.type_(), &mut features);
+            }
+        }
+        features
+    }
+
+    fn used_by_type_ref(type_ref: &TypeRef, features: &mut BTreeSet<Feature>) {
+        match type_ref.resolved() {
+            Some(ResolvedType::Container(Container::Map)) => { features.insert(Feature::Map); },
+            Some(ResolvedType::Container(Container::Set)) => { features.insert(Feature::Set); },
+            Some(ResolvedType::Container(Container::Option)) => { features.insert(Feature::Option); },
+            Some(ResolvedType::Container(Container::Array)) => { features.insert(Feature::Arrays); },
+            Some(ResolvedType::Primitive(Primitive::Bytes)) => { features.insert(Feature::Bytes); },
+            _ => {}
+        }
+        for param in type_ref.params() {
+            Feature::used_by_type_ref(param, features);
+        }
+    }
+}
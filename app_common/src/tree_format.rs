@@ -14,10 +14,15 @@ pub trait DisplayableNode : Sized {
 pub enum DisplayableAST<'a> {
     Root(&'a Root),
     File(&'a File),
+    ConstDef(&'a ConstDef),
     TypeDef(&'a TypeDef),
+    Doc(&'a [String]),
+    Annotation(&'a Annotation),
     Type(&'a Type),
     Struct(&'a Struct),
     Enum(&'a Enum),
+    CEnum(&'a CEnum),
+    EnumValue(&'a EnumValue),
     Field(&'a Field),
     TypeRef(&'a TypeRef),
     Primitive(&'a Primitive)
@@ -50,9 +55,8 @@ impl Indent {
 
     fn last_as(&self, last: Indentation) -> Indent {
         let mut tabs = self.tabs.clone();
-        match tabs.pop() {
-            Some(_) => tabs.push(last),
-            None => {}
+        if tabs.pop().is_some() {
+            tabs.push(last);
         }
         Indent {
             tabs
@@ -93,21 +97,50 @@ impl DisplayableNode for DisplayableAST<'_> {
     fn displayable(&self) -> String {
         match self {
             DisplayableAST::Root(_) => "Root".to_string(),
-            DisplayableAST::File(f) => format!("File({})", f.namespace().to_string()),
-            DisplayableAST::TypeDef(t) => format!("TypeDef({})", t.name().to_string()),
+            DisplayableAST::File(f) if *f.external() => format!("File({}, external)", f.namespace()),
+            DisplayableAST::File(f) => format!("File({})", f.namespace()),
+            DisplayableAST::ConstDef(c) => format!("ConstDef({} = {})", c.name(), c.value()),
+            DisplayableAST::TypeDef(t) if t.params().is_empty() => format!("TypeDef({})", t.name()),
+            DisplayableAST::TypeDef(t) => format!(
+                "TypeDef({}<{}>)",
+                t.name(),
+                t.params().iter().map(Name::to_string).collect::<Vec<String>>().join(" ")
+            ),
+            DisplayableAST::Doc(lines) => format!("Doc({})", lines.join(" ")),
+            DisplayableAST::Annotation(a) => {
+                let mut parts = vec![format!("@{}", a.name())];
+                parts.extend(a.args().iter().map(|(key, value)| format!("{}={}", key, value)));
+                format!("Annotation({})", parts.join(" "))
+            },
             DisplayableAST::Type(t) => format!(
                 "Type({})",
                 match &t {
                     Type::Primitive(_) => "Primitive",
                     Type::Struct(_) => "Struct",
                     Type::Enum(_) => "Enum",
-                    Type::List(_) => "List",
+                    Type::CEnum(_) => "CEnum",
+                    Type::Alias(_) => "Alias",
+                    Type::Newtype(_) => "Newtype",
                 }
             ),
-            DisplayableAST::Struct(_) => format!("Struct"),
-            DisplayableAST::Enum(_) => format!("Enum"),
-            DisplayableAST::Field(f) => format!("Field({})", f.name().to_string()),
-            DisplayableAST::TypeRef(t) => format!("TypeRef({})", t.name().to_string()),
+            DisplayableAST::Struct(_) => "Struct".to_string(),
+            DisplayableAST::Enum(_) => "Enum".to_string(),
+            DisplayableAST::CEnum(_) => "CEnum".to_string(),
+            DisplayableAST::EnumValue(v) => match v.discriminant() {
+                Some(discriminant) => format!("EnumValue({} = {})", v.name(), discriminant),
+                None => format!("EnumValue({})", v.name())
+            },
+            DisplayableAST::Field(f) => {
+                let optional = if *f.optional() { "?" } else { "" };
+                match f.default() {
+                    Some(default) => format!("Field({}{} = {})", f.name(), optional, default),
+                    None => format!("Field({}{})", f.name(), optional)
+                }
+            },
+            DisplayableAST::TypeRef(t) => match t.length() {
+                Some(length) => format!("TypeRef({} {})", t.name(), length),
+                None => format!("TypeRef({})", t.name())
+            },
             DisplayableAST::Primitive(p) => format!("Primitive({:?})", p),
         }
     }
@@ -115,25 +148,54 @@ impl DisplayableNode for DisplayableAST<'_> {
     fn children(&self) -> Vec<Self> {
         match self {
             DisplayableAST::Root(r) => r.files().iter().map(DisplayableAST::File).collect(),
-            DisplayableAST::File(f) => f.types().iter().map(DisplayableAST::TypeDef).collect(),
-            DisplayableAST::TypeDef(t) => vec![DisplayableAST::Type(t.type_())],
+            DisplayableAST::File(f) => f.consts().iter()
+                .map(DisplayableAST::ConstDef)
+                .chain(f.types().iter().map(DisplayableAST::TypeDef))
+                .collect(),
+            DisplayableAST::ConstDef(c) => doc_node(c.doc()).into_iter()
+                .chain(c.annotations().iter().map(DisplayableAST::Annotation))
+                .chain([DisplayableAST::TypeRef(c.type_())])
+                .collect(),
+            DisplayableAST::TypeDef(t) => doc_node(t.doc()).into_iter()
+                .chain(t.annotations().iter().map(DisplayableAST::Annotation))
+                .chain([DisplayableAST::Type(t.type_())])
+                .collect(),
+            DisplayableAST::Doc(_) | DisplayableAST::Annotation(_) => vec![],
             DisplayableAST::Type(t) => match &t {
                 Type::Primitive(p) => vec![DisplayableAST::Primitive(p)],
                 Type::Struct(s) => vec![DisplayableAST::Struct(s)],
                 Type::Enum(e) => vec![DisplayableAST::Enum(e)],
-                Type::List(t) => vec![DisplayableAST::Type(t)],
+                Type::CEnum(e) => vec![DisplayableAST::CEnum(e)],
+                Type::Alias(t) | Type::Newtype(t) => vec![DisplayableAST::TypeRef(t)],
             },
             DisplayableAST::Struct(s) => s.fields().iter().map(DisplayableAST::Field).collect(),
             DisplayableAST::Enum(e) => e.fields().iter().map(DisplayableAST::Field).collect(),
-            DisplayableAST::Field(f) => vec![DisplayableAST::TypeRef(f.type_())],
-            DisplayableAST::TypeRef(t) => t.params().iter().map(Box::as_ref).map(DisplayableAST::TypeRef).collect(),
+            DisplayableAST::CEnum(e) => e.backing().iter()
+                .map(DisplayableAST::TypeRef)
+                .chain(e.values().iter().map(DisplayableAST::EnumValue))
+                .collect(),
+            DisplayableAST::EnumValue(v) => doc_node(v.doc()).into_iter()
+                .chain(v.annotations().iter().map(DisplayableAST::Annotation))
+                .collect(),
+            DisplayableAST::Field(f) => doc_node(f.doc()).into_iter()
+                .chain(f.annotations().iter().map(DisplayableAST::Annotation))
+                .chain([DisplayableAST::TypeRef(f.type_())])
+                .collect(),
+            DisplayableAST::TypeRef(t) => t.params().iter().map(DisplayableAST::TypeRef).collect(),
             DisplayableAST::Primitive(_) => vec![],
         }
     }
 }
 
+fn doc_node(doc: &[String]) -> Option<DisplayableAST<'_>> {
+    match doc.is_empty() {
+        true => None,
+        false => Some(DisplayableAST::Doc(doc))
+    }
+}
+
 fn display_node_with_indent(node: &impl DisplayableNode, indent: Indent) {
-    println!("{}{}", indent.last_as(Indentation::Node), node.displayable());
+    eprintln!("{}{}", indent.last_as(Indentation::Node), node.displayable());
     let children = node.children();
     for (i, child) in node.children().iter().enumerate() {
         let indent =
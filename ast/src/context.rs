@@ -1,10 +1,59 @@
 use std::collections::HashMap;
 
-use crate::{Type, TypeDef};
+use serde::{Serialize, Deserialize};
 
-#[derive(Copy, Clone, Hash, PartialEq, Eq)]
-struct TypeId(usize);
+use crate::{Name, QualifiedName};
 
-struct Context {
-    types: HashMap<TypeId, Type>
-}
\ No newline at end of file
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TypeId(usize);
+
+/// Where a user-defined type was declared and how many generic parameters it takes.
+#[derive(Constructor, Clone, Getters, CopyGetters, Setters, Debug, PartialEq, Serialize, Deserialize)]
+#[get="pub"]
+pub struct TypeInfo {
+    namespace: QualifiedName,
+    name: Name,
+
+    #[serde(default)]
+    arity: usize
+}
+
+/// Every user-defined type across all files, keyed by the id that resolved `TypeRef`s link to.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Context {
+    types: HashMap<TypeId, TypeInfo>
+}
+
+impl TypeInfo {
+    pub fn qualified_name(&self) -> QualifiedName {
+        self.namespace.with_appended(&self.name)
+    }
+}
+
+impl Context {
+    pub fn new() -> Context {
+        Context::default()
+    }
+
+    /// Registers a type, returning `None` if the namespace already has a type with the same name.
+    /// Names are compared as generators write them, so `Foo` and `foo` are the same.
+    pub fn add(&mut self, info: TypeInfo) -> Option<TypeId> {
+        let name = info.name.to_upper_camel_case(&[]);
+        if self.types.values().any(|found| found.namespace == info.namespace && found.name.to_upper_camel_case(&[]) == name) {
+            return None;
+        }
+        let id = TypeId(self.types.len());
+        self.types.insert(id, info);
+        Some(id)
+    }
+
+    pub fn get(&self, id: TypeId) -> Option<&TypeInfo> {
+        self.types.get(&id)
+    }
+
+    pub fn lookup(&self, name: &QualifiedName) -> Option<TypeId> {
+        self.types.iter()
+            .find(|(_, info)| &info.qualified_name() == name)
+            .map(|(id, _)| *id)
+    }
+}
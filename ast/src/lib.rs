@@ -3,36 +3,197 @@
 extern crate serde;
 
 mod context;
+mod literal;
 mod name;
+mod resolve;
+mod span;
 
 pub use context::Context;
+pub use context::TypeId;
+pub use context::TypeInfo;
+pub use literal::Literal;
+pub use resolve::resolve;
+pub use resolve::Location;
+pub use resolve::ResolveError;
 pub use name::Name;
 pub use name::QualifiedName;
+pub use span::Span;
+
+use std::{collections::BTreeMap, path::PathBuf};
 
 use serde::Serialize;
 use serde::Deserialize;
 
 
-#[derive(Constructor, Clone, Getters, CopyGetters, Setters, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Constructor, Clone, Getters, CopyGetters, MutGetters, Setters, Debug, PartialEq, Serialize, Deserialize)]
 #[get="pub"]
+#[get_mut="pub"]
+#[set="pub"]
 pub struct Root {
-    files: Vec<File>
+    files: Vec<File>,
+
+    #[serde(default)]
+    context: Context
 }
 
-#[derive(Constructor, Clone, Getters, CopyGetters, Setters, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Constructor, Clone, Getters, CopyGetters, MutGetters, Setters, Debug, PartialEq, Serialize, Deserialize)]
 #[get="pub"]
+#[get_mut="pub"]
+#[set="pub"]
 pub struct File {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    path: Option<PathBuf>,
+
+    /// Set on files that were only loaded because another file imports them. Generators should
+    /// not emit their types.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    external: bool,
+
     namespace: QualifiedName,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    imports: Vec<Import>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    consts: Vec<ConstDef>,
+
     types: Vec<TypeDef>
 }
 
-#[derive(Constructor, Clone, Getters, CopyGetters, Setters, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Import {
+    /// `import a.b`, found as `a/b.dcg` under one of the search paths.
+    Namespace(QualifiedName),
+
+    /// `import "path/to/file.dcg"`, relative to the importing file or one of the search paths.
+    Path(PathBuf)
+}
+
+#[derive(Constructor, Clone, Getters, CopyGetters, MutGetters, Setters, Debug, PartialEq, Serialize, Deserialize)]
 #[get="pub"]
+#[get_mut="pub"]
+#[set="pub"]
 pub struct TypeDef {
     name: Name,
 
+    /// Declared generic parameters, referenced from fields through `ResolvedType::Param`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    params: Vec<Name>,
+
+    /// The `///` comments before the type, one line each, without the leading `///`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    doc: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    annotations: Vec<Annotation>,
+
+    #[serde(rename = "type")]
+    type_: Type,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    span: Option<Span>
+}
+
+/// `const NAME: T = value` at namespace scope. `resolve` checks that `T` is a primitive and that the
+/// value matches it.
+#[derive(Constructor, Clone, Getters, CopyGetters, MutGetters, Setters, Debug, PartialEq, Serialize, Deserialize)]
+#[get="pub"]
+#[get_mut="pub"]
+#[set="pub"]
+pub struct ConstDef {
+    name: Name,
+
     #[serde(rename = "type")]
-    type_: Type
+    type_: TypeRef,
+    value: Literal,
+
+    /// The `///` comments before the constant, one line each, without the leading `///`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    doc: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    annotations: Vec<Annotation>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    span: Option<Span>
+}
+
+/// `@name(key = value ...)` on a type, field or constant. dcg passes annotations on without interpreting them,
+/// generators look up the ones they know by name.
+#[derive(Constructor, Clone, Getters, CopyGetters, MutGetters, Setters, Debug, PartialEq, Serialize, Deserialize)]
+#[get="pub"]
+#[get_mut="pub"]
+#[set="pub"]
+pub struct Annotation {
+    name: String,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    args: BTreeMap<String, Literal>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    span: Option<Span>
+}
+
+impl Annotation {
+    pub fn arg(&self, key: &str) -> Option<&Literal> {
+        self.args.get(key)
+    }
+
+    /// Returns an argument's value if it is a string.
+    pub fn string_arg(&self, key: &str) -> Option<&str> {
+        match self.args.get(key) {
+            Some(Literal::String(s)) => Some(s),
+            _ => None
+        }
+    }
+}
+
+impl Root {
+    /// Returns the definition of a resolved type.
+    pub fn type_def(&self, id: TypeId) -> Option<&TypeDef> {
+        let info = self.context.get(id)?;
+        self.files.iter()
+            .filter(|file| file.namespace() == info.namespace())
+            .flat_map(|file| file.types())
+            .find(|type_def| type_def.name() == info.name())
+    }
+
+    /// Follows `type_ref` through any aliases to the type they name, stopping at a cycle.
+    pub fn unaliased<'a>(&'a self, mut type_ref: &'a TypeRef) -> &'a TypeRef {
+        let mut visited = vec![];
+        while let Some(ResolvedType::Defined(id)) = type_ref.resolved {
+            match self.type_def(id).map(TypeDef::type_) {
+                Some(Type::Alias(target)) if !visited.contains(&id) => type_ref = target,
+                _ => break
+            }
+            visited.push(id);
+        }
+        type_ref
+    }
+}
+
+impl TypeDef {
+    pub fn annotation(&self, name: &str) -> Option<&Annotation> {
+        self.annotations.iter().find(|a| a.name == name)
+    }
+}
+
+impl ConstDef {
+    pub fn annotation(&self, name: &str) -> Option<&Annotation> {
+        self.annotations.iter().find(|a| a.name == name)
+    }
+}
+
+impl EnumValue {
+    pub fn annotation(&self, name: &str) -> Option<&Annotation> {
+        self.annotations.iter().find(|a| a.name == name)
+    }
+}
+
+impl Field {
+    pub fn annotation(&self, name: &str) -> Option<&Annotation> {
+        self.annotations.iter().find(|a| a.name == name)
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -40,38 +201,171 @@ pub enum Type {
     Primitive(Primitive),
     Struct(Struct),
     Enum(Enum),
-    List(Box<Type>)
+    CEnum(CEnum),
+
+    /// `type UserId = UInt64`, another name for the same type.
+    Alias(TypeRef),
+
+    /// `newtype Email = String`, a distinct type holding a value of another.
+    Newtype(TypeRef)
 }
 
-#[derive(Constructor, Clone, Getters, CopyGetters, Setters, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Constructor, Clone, Getters, CopyGetters, MutGetters, Setters, Debug, PartialEq, Serialize, Deserialize)]
 #[get="pub"]
+#[get_mut="pub"]
+#[set="pub"]
 pub struct Struct {
     fields: Vec<Field>
 }
 
-#[derive(Constructor, Clone, Getters, CopyGetters, Setters, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Constructor, Clone, Getters, CopyGetters, MutGetters, Setters, Debug, PartialEq, Serialize, Deserialize)]
 #[get="pub"]
+#[get_mut="pub"]
+#[set="pub"]
 pub struct Enum {
     fields: Vec<Field>
 }
 
-#[derive(Constructor, Clone, Getters, CopyGetters, Setters, Debug, PartialEq, Serialize, Deserialize)]
+/// `enum Color : UInt8 { Red = 1 Green }`, an enum whose variants have no value but an integer
+/// discriminant.
+#[derive(Constructor, Clone, Getters, CopyGetters, MutGetters, Setters, Debug, PartialEq, Serialize, Deserialize)]
+#[get="pub"]
+#[get_mut="pub"]
+#[set="pub"]
+pub struct CEnum {
+    /// The integer primitive the discriminants are stored as. `Int32` if not given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    backing: Option<TypeRef>,
+    values: Vec<EnumValue>
+}
+
+#[derive(Constructor, Clone, Getters, CopyGetters, MutGetters, Setters, Debug, PartialEq, Serialize, Deserialize)]
+#[get="pub"]
+#[get_mut="pub"]
+#[set="pub"]
+pub struct EnumValue {
+    name: Name,
+
+    /// Filled in by `resolve` when not written, as one more than the previous value's, or 0 for the
+    /// first.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    discriminant: Option<i64>,
+
+    /// The `///` comments before the value, one line each, without the leading `///`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    doc: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    annotations: Vec<Annotation>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    span: Option<Span>
+}
+
+#[derive(Constructor, Clone, Getters, CopyGetters, MutGetters, Setters, Debug, PartialEq, Serialize, Deserialize)]
 #[get="pub"]
+#[get_mut="pub"]
+#[set="pub"]
 pub struct Field {
     name: Name,
 
+    /// Written `name?: T`. The field may be absent, as if its type were `Option<T>`. Only allowed on
+    /// struct fields.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    optional: bool,
+
     #[serde(rename = "type")]
-    type_: TypeRef
+    type_: TypeRef,
+
+    /// Only allowed on struct fields. Checked against `type_` by `resolve`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    default: Option<Literal>,
+
+    /// The `///` comments before the field or variant, one line each, without the leading `///`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    doc: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    annotations: Vec<Annotation>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    span: Option<Span>
 }
 
-#[derive(Constructor, Clone, Getters, CopyGetters, Setters, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Constructor, Clone, Getters, CopyGetters, MutGetters, Setters, Debug, PartialEq, Serialize, Deserialize)]
 #[get="pub"]
+#[get_mut="pub"]
+#[set="pub"]
 pub struct TypeRef {
     name: QualifiedName,
-    params: Vec<Box<TypeRef>>
+    params: Vec<TypeRef>,
+
+    /// The `N` of `Array<T N>`, written after the type parameters.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    length: Option<u64>,
+
+    /// Filled in by `resolve`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    resolved: Option<ResolvedType>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    span: Option<Span>
+}
+
+/// What a `TypeRef` names once it has been resolved.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ResolvedType {
+    Primitive(Primitive),
+    Container(Container),
+    Defined(TypeId),
+
+    /// The generic parameter at this index in the enclosing `TypeDef::params`.
+    Param(usize)
+}
+
+impl TypeRef {
+    /// Returns the primitive this reference resolved to, if any.
+    pub fn primitive(&self) -> Option<Primitive> {
+        match self.resolved {
+            Some(ResolvedType::Primitive(p)) => Some(p),
+            _ => None
+        }
+    }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+/// A builtin generic type. Its type parameters are the `params` of the `TypeRef` that names it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Container {
+    List,
+    Map,
+    Set,
+    Option,
+
+    /// `Array<T N>`, exactly `N` values of `T`. The only type that takes a length.
+    Array
+}
+
+impl Container {
+    pub fn from_name(name: &str) -> Option<Container> {
+        match name {
+            "List" => Some(Container::List),
+            "Map" => Some(Container::Map),
+            "Set" => Some(Container::Set),
+            "Option" => Some(Container::Option),
+            "Array" => Some(Container::Array),
+            _ => None
+        }
+    }
+
+    pub fn arity(&self) -> usize {
+        match self {
+            Container::List | Container::Set | Container::Option | Container::Array => 1,
+            Container::Map => 2
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Primitive {
     Unit,
     Bool,
@@ -85,5 +379,30 @@ pub enum Primitive {
     UInt64,
     Float32,
     Float64,
-    String
+    String,
+
+    /// A blob of binary data.
+    Bytes
+}
+
+impl Primitive {
+    pub fn from_name(name: &str) -> Option<Primitive> {
+        match name {
+            "Unit" => Some(Primitive::Unit),
+            "Bool" => Some(Primitive::Bool),
+            "Int8" => Some(Primitive::Int8),
+            "Int16" => Some(Primitive::Int16),
+            "Int32" => Some(Primitive::Int32),
+            "Int64" => Some(Primitive::Int64),
+            "UInt8" => Some(Primitive::UInt8),
+            "UInt16" => Some(Primitive::UInt16),
+            "UInt32" => Some(Primitive::UInt32),
+            "UInt64" => Some(Primitive::UInt64),
+            "Float32" => Some(Primitive::Float32),
+            "Float64" => Some(Primitive::Float64),
+            "String" => Some(Primitive::String),
+            "Bytes" => Some(Primitive::Bytes),
+            _ => None
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,58 @@
+use std::fmt::Display;
+
+use serde::{Serialize, Deserialize};
+
+use crate::QualifiedName;
+
+/// A value written in the source: the default value of a field, which `resolve` checks against the
+/// field's type, or an annotation argument.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Literal {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+
+    /// `[a b]`, used for lists and sets.
+    List(Vec<Literal>),
+
+    /// `{k: v}`.
+    Map(Vec<(Literal, Literal)>),
+
+    /// `Color.Red`. As a default value it must name a variant of the field's enum that has no value.
+    Variant(QualifiedName)
+}
+
+/// Writes the literal as it would appear in the source.
+impl Display for Literal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Literal::Bool(b) => write!(f, "{}", b),
+            Literal::Int(i) => write!(f, "{}", i),
+            Literal::Float(x) => write!(f, "{:?}", x),
+            Literal::String(s) => {
+                f.write_str("\"")?;
+                for ch in s.chars() {
+                    match ch {
+                        '"' => f.write_str("\\\"")?,
+                        '\\' => f.write_str("\\\\")?,
+                        '\n' => f.write_str("\\n")?,
+                        '\r' => f.write_str("\\r")?,
+                        '\t' => f.write_str("\\t")?,
+                        ch => write!(f, "{}", ch)?
+                    }
+                }
+                f.write_str("\"")
+            },
+            Literal::List(items) => {
+                let items = items.iter().map(Literal::to_string).collect::<Vec<String>>();
+                write!(f, "[{}]", items.join(" "))
+            },
+            Literal::Map(entries) => {
+                let entries = entries.iter().map(|(k, v)| format!("{}: {}", k, v)).collect::<Vec<String>>();
+                write!(f, "{{{}}}", entries.join(" "))
+            },
+            Literal::Variant(name) => write!(f, "{}", name)
+        }
+    }
+}
@@ -1,5 +1,9 @@
+use std::fmt::Display;
+
 use serde::{Serialize,Deserialize};
 
+use crate::Span;
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum NameCase {
     SnakeCase,
@@ -15,10 +19,14 @@ pub struct Name {
     case: NameCase
 }
 
-#[derive(Constructor, Clone, Getters, CopyGetters, Setters, Debug, PartialEq, Serialize, Deserialize)]
-#[get]
+#[derive(Clone, Getters, CopyGetters, Setters, Debug, Serialize, Deserialize)]
+#[get="pub"]
 pub struct QualifiedName {
-    names: Vec<Name>
+    names: Vec<Name>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[set="pub"]
+    span: Option<Span>
 }
 impl Name {
     pub fn from(name: &str) -> Name {
@@ -36,14 +44,14 @@ impl Name {
                 names.push(current_name);
                 current_name = String::new()
             }
-            current_name = current_name + ch.to_string().as_str();
+            current_name += ch.to_string().as_str();
             last_char_was_lowercase = ch.is_lowercase();
         }
         if !current_name.is_empty() {
             names.push(current_name)
         }
 
-        return Name { tokens: names, case: NameCase::Fixed };
+        Name { tokens: names, case: NameCase::Fixed }
     }
 
     pub fn with_prepended(&self, prepended_token: &str) -> Name {
@@ -51,7 +59,7 @@ impl Name {
         for token in self.tokens.clone() {
             tokens.push(token);
         }
-        return Name { tokens: tokens, case: self.case };
+        Name { tokens, case: self.case }
     }
 
     fn check_reserved(s: String, reserved: &[&str]) -> String {
@@ -60,11 +68,11 @@ impl Name {
                 return s + "_";
             }
         }
-        return s;
+        s
     }
 
     pub fn to_fixed_case(&self) -> String {
-        return self.tokens.join("");
+        self.tokens.join("")
     }
 
     pub fn to_snake_case(&self, reserved: &[&str]) -> String {
@@ -72,7 +80,7 @@ impl Name {
             .map(|x| { x.to_lowercase() })
             .collect::<Vec<String>>().join("_");
 
-        return Name::check_reserved(s, reserved);
+        Name::check_reserved(s, reserved)
     }
 
     pub fn to_screaming_snake_case(&self,  reserved: &[&str]) -> String {
@@ -80,7 +88,7 @@ impl Name {
             .map(|x| { x.to_uppercase() })
             .collect::<Vec<String>>().join("_");
 
-        return Name::check_reserved(s, reserved);
+        Name::check_reserved(s, reserved)
     }
 
     pub fn to_upper_camel_case(&self, reserved: &[&str]) -> String {
@@ -95,11 +103,11 @@ impl Name {
             .collect::<Vec<String>>()
             .join("");
 
-        return Name::check_reserved(s, reserved);
+        Name::check_reserved(s, reserved)
     }
 
     pub fn to_lower_camel_case(&self, reserved: &[&str]) -> String {
-        if self.tokens.len() == 0 {
+        if self.tokens.is_empty() {
             return String::new()
         }
 
@@ -116,58 +124,59 @@ impl Name {
                 .collect::<Vec<String>>()
                 .join("").as_str();
 
-        return Name::check_reserved(s, reserved);
+        Name::check_reserved(s, reserved)
     }
 }
 
-impl ToString for Name {
-    fn to_string(&self) -> String {
-        match self.case {
+impl Display for Name {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self.case {
             NameCase::Fixed => self.to_fixed_case(),
             NameCase::LowerCamelCase => self.to_lower_camel_case(&[]),
             NameCase::UpperCamelCase => self.to_upper_camel_case(&[]),
             NameCase::ScreamingSnakeCase => self.to_screaming_snake_case(&[]),
             NameCase::SnakeCase => self.to_snake_case(&[])
-        }
+        };
+        f.write_str(&s)
     }
 }
 
 impl QualifiedName {
+    pub fn new(names: Vec<Name>) -> Self {
+        QualifiedName { names, span: None }
+    }
+
     pub fn empty() -> Self {
-        QualifiedName { names: vec!() }
+        QualifiedName::new(vec!())
     }
 
     pub fn with_prepended(&self, name: &Name) -> QualifiedName {
-        QualifiedName { names: std::iter::once(name.clone()).chain(self.names().clone()).collect() }
+        QualifiedName::new(std::iter::once(name.clone()).chain(self.names().clone()).collect())
     }
 
     pub fn with_appended(&self, name: &Name) -> QualifiedName {
         let mut names = self.names().clone();
         names.push(name.clone());
-        QualifiedName { names: names }
+        QualifiedName::new(names)
     }
 
     pub fn head(&self) -> Option<&Name> {
-        if self.names.len() == 0 {
+        if self.names.is_empty() {
             return None;
         }
-        Some(self.names.get(0).unwrap())
+        Some(self.names.first().unwrap())
     }
 
     pub fn tail(&self) -> QualifiedName {
         let mut tail_names = self.names.clone();
         tail_names.remove(0);
-        QualifiedName {
-            names: tail_names
-        }
+        QualifiedName::new(tail_names)
     }
 
     pub fn parent(&self) -> QualifiedName {
         match self.names.split_last() {
             Some((_last,names)) =>
-                QualifiedName {
-                    names: names.to_vec()
-                },
+                QualifiedName::new(names.to_vec()),
             None => QualifiedName::empty()
         }
     }
@@ -187,18 +196,25 @@ impl QualifiedName {
             }
         }
 
-        return true;
+        true
     }
 }
 
 impl From<Vec<&str>> for QualifiedName {
     fn from(names: Vec<&str>) -> QualifiedName {
-        QualifiedName { names: names.iter().map(|n| Name::from(&n)).collect() }
+        QualifiedName::new(names.iter().map(|n| Name::from(n)).collect())
+    }
+}
+
+/// Qualified names compare by their names alone so that lookups aren't affected by where a name was written.
+impl PartialEq for QualifiedName {
+    fn eq(&self, other: &Self) -> bool {
+        self.names == other.names
     }
 }
 
-impl ToString for QualifiedName {
-    fn to_string(&self) -> String {
-        self.names.iter().map(Name::to_string).collect::<Vec<String>>().join(".")
+impl Display for QualifiedName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.names.iter().map(Name::to_string).collect::<Vec<String>>().join("."))
     }
 }
\ No newline at end of file
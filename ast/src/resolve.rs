@@ -0,0 +1,1145 @@
+use std::{collections::HashMap, fmt::Display, path::PathBuf};
+
+use crate::*;
+
+/// The file and span an error was found at, when known.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Location {
+    pub path: Option<PathBuf>,
+    pub span: Option<Span>
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ResolveError {
+    UnknownType {
+        name: QualifiedName,
+        namespace: QualifiedName,
+        type_def: Name,
+        location: Location
+    },
+    DuplicateType {
+        name: QualifiedName,
+        location: Location
+    },
+    DuplicateConst {
+        name: QualifiedName,
+        location: Location
+    },
+    WrongArity {
+        name: QualifiedName,
+        expected: usize,
+        found: usize,
+        location: Location
+    },
+    WrongLength {
+        name: QualifiedName,
+        expected: bool,
+        location: Location
+    },
+    InvalidDefault {
+        field: Name,
+        message: String,
+        location: Location
+    },
+    OptionalVariant {
+        variant: Name,
+        location: Location
+    },
+    InvalidConst {
+        name: Name,
+        message: String,
+        location: Location
+    },
+    InvalidEnum {
+        type_def: Name,
+        message: String,
+        location: Location
+    },
+    AliasCycle {
+        name: QualifiedName,
+        location: Location
+    },
+    NewtypeCycle {
+        name: QualifiedName,
+        location: Location
+    },
+    InfiniteType {
+        name: QualifiedName,
+        location: Location
+    },
+    UnusedParam {
+        param: Name,
+        type_def: Name,
+        location: Location
+    },
+    InvalidKey {
+        name: QualifiedName,
+        location: Location
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(path) = &self.path {
+            write!(f, "{}:", path.display())?;
+        }
+        if let Some(span) = &self.span {
+            write!(f, "{}:{}:", span.line(), span.column())?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for ResolveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResolveError::UnknownType { name, namespace, type_def, location } =>
+                write!(f, "{} Unknown type \"{}\" in {}.{}", location, name, namespace, type_def),
+            ResolveError::DuplicateType { name, location } =>
+                write!(f, "{} Duplicate definition of type \"{}\"", location, name),
+            ResolveError::DuplicateConst { name, location } =>
+                write!(f, "{} Duplicate definition of constant \"{}\"", location, name),
+            ResolveError::WrongArity { name, expected, found, location } =>
+                write!(f, "{} Type \"{}\" takes {} type parameter(s) but {} were given", location, name, expected, found),
+            ResolveError::WrongLength { name, expected: true, location } =>
+                write!(f, "{} Type \"{}\" takes a length after its type parameter(s)", location, name),
+            ResolveError::WrongLength { name, expected: false, location } =>
+                write!(f, "{} Type \"{}\" doesn't take a length", location, name),
+            ResolveError::InvalidDefault { field, message, location } =>
+                write!(f, "{} Invalid default value for field \"{}\": {}", location, field, message),
+            ResolveError::OptionalVariant { variant, location } =>
+                write!(f, "{} Enum variant \"{}\" can't be optional", location, variant),
+            ResolveError::InvalidConst { name, message, location } =>
+                write!(f, "{} Invalid constant \"{}\": {}", location, name, message),
+            ResolveError::InvalidEnum { type_def, message, location } =>
+                write!(f, "{} Invalid enum \"{}\": {}", location, type_def, message),
+            ResolveError::AliasCycle { name, location } =>
+                write!(f, "{} Type alias \"{}\" refers to itself", location, name),
+            ResolveError::NewtypeCycle { name, location } =>
+                write!(f, "{} Newtype \"{}\" contains itself outside of a List, Set or Map", location, name),
+            ResolveError::InfiniteType { name, location } =>
+                write!(f, "{} Type \"{}\" contains itself outside of a List, Set or Map", location, name),
+            ResolveError::UnusedParam { param, type_def, location } =>
+                write!(f, "{} Type parameter \"{}\" of \"{}\" is never used", location, param, type_def),
+            ResolveError::InvalidKey { name, location } =>
+                write!(f, "{} Type \"{}\" can't be hashed, so it can't be a Map key or a Set value", location, name)
+        }
+    }
+}
+
+struct Resolver<'a> {
+    context: &'a Context,
+    path: &'a Option<PathBuf>,
+    namespace: &'a QualifiedName,
+
+    /// The type or constant being resolved, named in errors.
+    type_def: &'a Name,
+    params: &'a [Name],
+
+    /// Every defined type, used to check aliases, constants and defaults. Empty while `TypeRef`s are
+    /// still being resolved.
+    types: &'a HashMap<TypeId, &'a Type>,
+    errors: Vec<ResolveError>
+}
+
+impl Resolver<'_> {
+    /// Looks a name up as a generic parameter of the enclosing type, a primitive, a builtin, a type
+    /// in the current namespace, a type in a namespace nested in the current one, and finally as a
+    /// fully qualified name.
+    fn lookup(&self, name: &QualifiedName) -> Option<ResolvedType> {
+        if let [single] = name.names().as_slice() {
+            if let Some(index) = self.params.iter().position(|p| p == single) {
+                return Some(ResolvedType::Param(index));
+            }
+            if let Some(primitive) = Primitive::from_name(&single.to_string()) {
+                return Some(ResolvedType::Primitive(primitive));
+            }
+            if let Some(container) = Container::from_name(&single.to_string()) {
+                return Some(ResolvedType::Container(container));
+            }
+        }
+
+        let relative = QualifiedName::new(
+            self.namespace.names().iter().chain(name.names()).cloned().collect()
+        );
+        self.context.lookup(&relative)
+            .or_else(|| self.context.lookup(name))
+            .map(ResolvedType::Defined)
+    }
+
+    fn arity(&self, resolved: ResolvedType) -> usize {
+        match resolved {
+            ResolvedType::Primitive(_) | ResolvedType::Param(_) => 0,
+            ResolvedType::Container(container) => container.arity(),
+            ResolvedType::Defined(id) => self.context.get(id).map(|info| *info.arity()).unwrap_or(0)
+        }
+    }
+
+    fn resolve_type_ref(&mut self, type_ref: &mut TypeRef) {
+        for param in type_ref.params_mut() {
+            self.resolve_type_ref(param);
+        }
+
+        match self.lookup(type_ref.name()) {
+            Some(resolved) => {
+                let expected = self.arity(resolved);
+                if expected != type_ref.params().len() {
+                    self.errors.push(ResolveError::WrongArity {
+                        name: type_ref.name().clone(),
+                        expected,
+                        found: type_ref.params().len(),
+                        location: Location { path: self.path.clone(), span: *type_ref.span() }
+                    });
+                }
+                let expected = resolved == ResolvedType::Container(Container::Array);
+                if expected != type_ref.length().is_some() {
+                    self.errors.push(ResolveError::WrongLength {
+                        name: type_ref.name().clone(),
+                        expected,
+                        location: Location { path: self.path.clone(), span: *type_ref.span() }
+                    });
+                }
+                type_ref.set_resolved(Some(resolved));
+            },
+            None => self.errors.push(ResolveError::UnknownType {
+                name: type_ref.name().clone(),
+                namespace: self.namespace.clone(),
+                type_def: self.type_def.clone(),
+                location: Location { path: self.path.clone(), span: *type_ref.span() }
+            })
+        }
+    }
+
+    fn resolve_type_def(&mut self, type_def: &mut TypeDef) {
+        let span = *type_def.span();
+        let (fields, is_enum) = match type_def.type__mut() {
+            Type::Struct(s) => (s.fields_mut(), false),
+            Type::Enum(e) => (e.fields_mut(), true),
+            Type::CEnum(e) => return self.resolve_c_enum(e, span),
+            Type::Alias(target) | Type::Newtype(target) => return self.resolve_type_ref(target),
+            Type::Primitive(_) => return
+        };
+        if is_enum && fields.is_empty() {
+            self.errors.push(ResolveError::InvalidEnum {
+                type_def: self.type_def.clone(),
+                message: "an enum needs at least one variant".to_string(),
+                location: Location { path: self.path.clone(), span }
+            });
+        }
+        for field in fields.iter_mut() {
+            if is_enum && *field.optional() {
+                self.errors.push(ResolveError::OptionalVariant {
+                    variant: field.name().clone(),
+                    location: Location { path: self.path.clone(), span: *field.span() }
+                });
+            }
+            self.resolve_type_ref(field.type__mut());
+        }
+
+        // Rust rejects a type parameter that no field uses.
+        for (index, param) in self.params.iter().enumerate() {
+            if !fields.iter().any(|field| uses_param(field.type_(), index)) {
+                self.errors.push(ResolveError::UnusedParam {
+                    param: param.clone(),
+                    type_def: self.type_def.clone(),
+                    location: Location { path: self.path.clone(), span }
+                });
+            }
+        }
+    }
+
+    /// Checks the backing type and fills in the discriminants that weren't written, which must then
+    /// be distinct and fit the backing type.
+    fn resolve_c_enum(&mut self, e: &mut CEnum, span: Option<Span>) {
+        if e.values().is_empty() {
+            self.errors.push(ResolveError::InvalidEnum {
+                type_def: self.type_def.clone(),
+                message: "an enum needs at least one value".to_string(),
+                location: Location { path: self.path.clone(), span }
+            });
+        }
+        let mut backing = Primitive::Int32;
+        if let Some(type_ref) = e.backing_mut() {
+            self.resolve_type_ref(type_ref);
+            match type_ref.resolved() {
+                Some(ResolvedType::Primitive(primitive @ (Primitive::Int8 | Primitive::Int16 | Primitive::Int32 | Primitive::Int64
+                    | Primitive::UInt8 | Primitive::UInt16 | Primitive::UInt32 | Primitive::UInt64))) => backing = *primitive,
+                Some(_) => return self.errors.push(ResolveError::InvalidEnum {
+                    type_def: self.type_def.clone(),
+                    message: format!("the backing type must be an integer primitive, not {}", type_ref.name()),
+                    location: Location { path: self.path.clone(), span: *type_ref.span() }
+                }),
+                None => return
+            }
+        }
+
+        let mut next = Some(0);
+        for value in e.values_mut().iter_mut() {
+            let discriminant = match (*value.discriminant(), next) {
+                (Some(discriminant), _) | (None, Some(discriminant)) => discriminant,
+                (None, None) => {
+                    self.errors.push(ResolveError::InvalidEnum {
+                        type_def: self.type_def.clone(),
+                        message: format!("the discriminant of \"{}\" overflows", value.name()),
+                        location: Location { path: self.path.clone(), span: *value.span() }
+                    });
+                    return;
+                }
+            };
+            value.set_discriminant(Some(discriminant));
+            next = discriminant.checked_add(1);
+        }
+
+        let mut seen: Vec<(&Name, i64)> = vec![];
+        for value in e.values() {
+            let discriminant = value.discriminant().unwrap_or_default();
+            let message = if let Err(message) = Resolver::check_primitive(backing, &Literal::Int(discriminant)) {
+                Some(format!("the discriminant of \"{}\": {}", value.name(), message))
+            } else if seen.iter().any(|(name, _)| *name == value.name()) {
+                Some(format!("duplicate value \"{}\"", value.name()))
+            } else if let Some((name, _)) = seen.iter().find(|(_, d)| *d == discriminant) {
+                Some(format!("\"{}\" and \"{}\" have the same discriminant {}", name, value.name(), discriminant))
+            } else {
+                None
+            };
+            if let Some(message) = message {
+                self.errors.push(ResolveError::InvalidEnum {
+                    type_def: self.type_def.clone(),
+                    message,
+                    location: Location { path: self.path.clone(), span: *value.span() }
+                });
+            }
+            seen.push((value.name(), discriminant));
+        }
+    }
+
+    /// Follows `type_ref` through any aliases to the type they name, stopping at a cycle.
+    fn unaliased<'b>(&'b self, type_ref: &'b TypeRef) -> &'b TypeRef {
+        self.underlying(type_ref, false)
+    }
+
+    /// Follows `type_ref` through any aliases, and also newtypes if `newtypes`, stopping at a cycle.
+    fn underlying<'b>(&'b self, mut type_ref: &'b TypeRef, newtypes: bool) -> &'b TypeRef {
+        let mut visited = vec![];
+        while let Some(ResolvedType::Defined(id)) = type_ref.resolved() {
+            match self.types.get(id) {
+                Some(Type::Alias(target)) if !visited.contains(id) => type_ref = target,
+                Some(Type::Newtype(target)) if newtypes && !visited.contains(id) => type_ref = target,
+                _ => break
+            }
+            visited.push(*id);
+        }
+        type_ref
+    }
+
+    /// Whether `type_ref` names the alias `id`, directly or through other aliases.
+    fn refers_to(&self, id: TypeId, type_ref: &TypeRef, visited: &mut Vec<TypeId>) -> bool {
+        if type_ref.params().iter().any(|param| self.refers_to(id, param, visited)) {
+            return true;
+        }
+        match type_ref.resolved() {
+            Some(ResolvedType::Defined(found)) if *found == id => true,
+            Some(ResolvedType::Defined(found)) if !visited.contains(found) => {
+                visited.push(*found);
+                match self.types.get(found) {
+                    Some(Type::Alias(target)) => self.refers_to(id, target, visited),
+                    _ => false
+                }
+            },
+            _ => false
+        }
+    }
+
+    /// Whether a value of `type_ref` holds a value of the type `id` in place, looking through
+    /// aliases, newtypes, `Option`s, `Array`s, the fields of structs and enums and the generic
+    /// arguments they hold in place. A `List`, `Set` or `Map` holds its values elsewhere.
+    fn contains(&self, id: TypeId, type_ref: &TypeRef, visited: &mut Vec<TypeId>) -> bool {
+        match type_ref.resolved() {
+            Some(ResolvedType::Container(Container::Option | Container::Array)) =>
+                type_ref.params().iter().any(|param| self.contains(id, param, visited)),
+            Some(ResolvedType::Defined(found)) if *found == id => true,
+            Some(ResolvedType::Defined(found)) => {
+                let found = *found;
+                if type_ref.params().iter().enumerate()
+                    .any(|(index, param)| self.contains(id, param, visited) && self.holds_param(found, index, &mut vec![])) {
+                    return true;
+                }
+                if visited.contains(&found) {
+                    return false;
+                }
+                visited.push(found);
+                match self.types.get(&found) {
+                    Some(Type::Alias(target) | Type::Newtype(target)) => self.contains(id, target, visited),
+                    Some(Type::Struct(Struct { fields }) | Type::Enum(Enum { fields })) =>
+                        fields.iter().any(|field| self.contains(id, field.type_(), visited)),
+                    _ => false
+                }
+            },
+            _ => false
+        }
+    }
+
+    /// Whether the type `id` holds a value of its generic parameter `index` in place.
+    fn holds_param(&self, id: TypeId, index: usize, visited: &mut Vec<(TypeId, usize)>) -> bool {
+        if visited.contains(&(id, index)) {
+            return false;
+        }
+        visited.push((id, index));
+        match self.types.get(&id) {
+            Some(Type::Struct(Struct { fields }) | Type::Enum(Enum { fields })) =>
+                fields.iter().any(|field| self.refers_to_param(field.type_(), index, visited)),
+            _ => false
+        }
+    }
+
+    /// Whether a value of `type_ref` holds a value of the generic parameter `index` in place.
+    fn refers_to_param(&self, type_ref: &TypeRef, index: usize, visited: &mut Vec<(TypeId, usize)>) -> bool {
+        match type_ref.resolved() {
+            Some(ResolvedType::Param(found)) => *found == index,
+            Some(ResolvedType::Container(Container::Option | Container::Array)) =>
+                type_ref.params().iter().any(|param| self.refers_to_param(param, index, visited)),
+            Some(ResolvedType::Defined(found)) => type_ref.params().iter().enumerate()
+                .any(|(i, param)| self.refers_to_param(param, index, visited) && self.holds_param(*found, i, visited)),
+            _ => false
+        }
+    }
+
+    /// An alias can't name itself, which would make it infinite. A newtype, struct or enum can, but
+    /// only through a `List`, `Set` or `Map`.
+    fn check_cycles(&mut self, type_def: &TypeDef) {
+        let name = self.namespace.with_appended(type_def.name());
+        let Some(id) = self.context.lookup(&name) else {
+            return;
+        };
+        let location = Location { path: self.path.clone(), span: *type_def.span() };
+        match type_def.type_() {
+            Type::Alias(target) if self.refers_to(id, target, &mut vec![]) =>
+                self.errors.push(ResolveError::AliasCycle { name, location }),
+            Type::Newtype(target) if self.contains(id, target, &mut vec![]) =>
+                self.errors.push(ResolveError::NewtypeCycle { name, location }),
+            Type::Struct(Struct { fields }) | Type::Enum(Enum { fields })
+                if fields.iter().any(|field| self.contains(id, field.type_(), &mut vec![])) =>
+                self.errors.push(ResolveError::InfiniteType { name, location }),
+            _ => {}
+        }
+    }
+
+    /// Constants may have a primitive type other than `Bytes`, or an alias of one.
+    fn check_const(&mut self, const_def: &ConstDef) {
+        let result = match self.unaliased(const_def.type_()).resolved() {
+            Some(ResolvedType::Primitive(Primitive::Bytes)) => Err("constants can't have type Bytes".to_string()),
+            Some(ResolvedType::Primitive(primitive)) => Resolver::check_primitive(*primitive, const_def.value()),
+            Some(_) => Err(format!("constants must have a primitive type, not {}", const_def.type_().name())),
+            None => return
+        };
+        if let Err(message) = result {
+            self.errors.push(ResolveError::InvalidConst {
+                name: const_def.name().clone(),
+                message,
+                location: Location { path: self.path.clone(), span: *const_def.span() }
+            });
+        }
+    }
+
+    /// Integers are stored as `i64`, so `UInt64` defaults are limited to `i64::MAX`. `Bytes` are
+    /// written as a list of `UInt8`s.
+    fn check_primitive(primitive: Primitive, literal: &Literal) -> Result<(), String> {
+        let range = match primitive {
+            Primitive::Int8 => Some((i8::MIN as i64, i8::MAX as i64)),
+            Primitive::Int16 => Some((i16::MIN as i64, i16::MAX as i64)),
+            Primitive::Int32 => Some((i32::MIN as i64, i32::MAX as i64)),
+            Primitive::Int64 => Some((i64::MIN, i64::MAX)),
+            Primitive::UInt8 => Some((0, u8::MAX as i64)),
+            Primitive::UInt16 => Some((0, u16::MAX as i64)),
+            Primitive::UInt32 => Some((0, u32::MAX as i64)),
+            Primitive::UInt64 => Some((0, i64::MAX)),
+            _ => None
+        };
+        match (primitive, literal) {
+            (_, Literal::Int(i)) if range.is_some_and(|(min, max)| *i < min || *i > max) =>
+                Err(format!("{} is out of range for {:?}", i, primitive)),
+            (_, Literal::Int(_)) if range.is_some() => Ok(()),
+            (Primitive::Float32 | Primitive::Float64, Literal::Int(_) | Literal::Float(_)) => Ok(()),
+            (Primitive::Bool, Literal::Bool(_)) => Ok(()),
+            (Primitive::String, Literal::String(_)) => Ok(()),
+            (Primitive::Bytes, Literal::List(items)) =>
+                items.iter().try_for_each(|item| Resolver::check_primitive(Primitive::UInt8, item)),
+            _ => Err(format!("{} is not a valid {:?}", literal, primitive))
+        }
+    }
+
+    /// Checks that `name` is `Enum.Variant`, where the enum is `id` and the variant has no value.
+    fn check_variant(&self, id: TypeId, name: &QualifiedName) -> Result<(), String> {
+        let enum_name = name.parent();
+        let variant = name.last().filter(|_| !enum_name.names().is_empty());
+        match (self.lookup(&enum_name), variant) {
+            (Some(ResolvedType::Defined(found)), Some(variant)) if found == id => {
+                let found = match self.types.get(&id) {
+                    Some(Type::Enum(e)) => e.fields().iter()
+                        .any(|field| field.name() == variant && field.type_().primitive() == Some(Primitive::Unit)),
+                    Some(Type::CEnum(e)) => e.values().iter().any(|value| value.name() == variant),
+                    _ => false
+                };
+                match found {
+                    true => Ok(()),
+                    false => Err(format!("\"{}\" has no variant \"{}\" without a value", enum_name, variant))
+                }
+            },
+            _ => Err(format!("{} is not a variant of the field's type", name))
+        }
+    }
+
+    /// Checks a default value against the type it is given for, returning why it doesn't match. The
+    /// value of an alias or a newtype is that of the type it names.
+    fn check_literal(&self, literal: &Literal, type_ref: &TypeRef) -> Result<(), String> {
+        let type_ref = self.underlying(type_ref, true);
+        let params = type_ref.params();
+        match (type_ref.resolved(), literal) {
+            (Some(ResolvedType::Primitive(primitive)), _) => Resolver::check_primitive(*primitive, literal),
+            (Some(ResolvedType::Container(Container::List | Container::Set)), Literal::List(items)) =>
+                items.iter().try_for_each(|item| self.check_literal(item, &params[0])),
+            (Some(ResolvedType::Container(Container::Map)), Literal::Map(entries)) =>
+                entries.iter().try_for_each(|(key, value)| {
+                    self.check_literal(key, &params[0])?;
+                    self.check_literal(value, &params[1])
+                }),
+            (Some(ResolvedType::Container(Container::Option)), _) => self.check_literal(literal, &params[0]),
+            (Some(ResolvedType::Container(Container::Array)), Literal::List(items)) => match *type_ref.length() {
+                Some(length) if items.len() as u64 != length => Err(format!("expected {} values but found {}", length, items.len())),
+                _ => items.iter().try_for_each(|item| self.check_literal(item, &params[0]))
+            },
+            (Some(ResolvedType::Defined(id)), Literal::Variant(name)) => self.check_variant(*id, name),
+            (Some(ResolvedType::Param(_)), _) => Err("fields of a generic type can't have a default value".to_string()),
+            _ => Err(format!("{} is not a valid {}", literal, type_ref.name()))
+        }
+    }
+
+    /// Whether values of `type_ref` can be hashed and compared for equality. Floats can't, nor can
+    /// a `Map`, a `Set` or any type holding one. Generic parameters are assumed to be hashable.
+    fn is_hashable(&self, type_ref: &TypeRef, visited: &mut Vec<TypeId>) -> bool {
+        if !type_ref.params().iter().all(|param| self.is_hashable(param, visited)) {
+            return false;
+        }
+        match type_ref.resolved() {
+            Some(ResolvedType::Primitive(Primitive::Float32 | Primitive::Float64)) => false,
+            Some(ResolvedType::Container(Container::Map | Container::Set)) => false,
+            Some(ResolvedType::Defined(id)) if !visited.contains(id) => {
+                visited.push(*id);
+                match self.types.get(id) {
+                    Some(Type::Struct(Struct { fields }) | Type::Enum(Enum { fields })) =>
+                        fields.iter().all(|field| self.is_hashable(field.type_(), visited)),
+                    Some(Type::Alias(target) | Type::Newtype(target)) => self.is_hashable(target, visited),
+                    _ => true
+                }
+            },
+            _ => true
+        }
+    }
+
+    /// Checks that the keys of every `Map` and the values of every `Set` in `type_ref` are hashable.
+    fn check_keys(&mut self, type_ref: &TypeRef) {
+        if let Some(ResolvedType::Container(Container::Map | Container::Set)) = type_ref.resolved() {
+            let key = &type_ref.params()[0];
+            if !self.is_hashable(key, &mut vec![]) {
+                self.errors.push(ResolveError::InvalidKey {
+                    name: key.name().clone(),
+                    location: Location { path: self.path.clone(), span: *key.span() }
+                });
+            }
+        }
+        for param in type_ref.params() {
+            self.check_keys(param);
+        }
+    }
+
+    /// Checks default values, and the keys of the maps and sets the type uses.
+    fn check_fields(&mut self, type_def: &TypeDef) {
+        let (fields, is_struct) = match type_def.type_() {
+            Type::Struct(s) => (s.fields(), true),
+            Type::Enum(e) => (e.fields(), false),
+            Type::Alias(target) | Type::Newtype(target) => return self.check_keys(target),
+            Type::Primitive(_) | Type::CEnum(_) => return
+        };
+        for field in fields {
+            self.check_keys(field.type_());
+            let Some(default) = field.default() else {
+                continue;
+            };
+            let result = match is_struct {
+                true => self.check_literal(default, field.type_()),
+                false => Err("enum variants can't have a default value".to_string())
+            };
+            if let Err(message) = result {
+                self.errors.push(ResolveError::InvalidDefault {
+                    field: field.name().clone(),
+                    message,
+                    location: Location { path: self.path.clone(), span: *field.span() }
+                });
+            }
+        }
+    }
+}
+
+fn uses_param(type_ref: &TypeRef, index: usize) -> bool {
+    *type_ref.resolved() == Some(ResolvedType::Param(index)) || type_ref.params().iter().any(|param| uses_param(param, index))
+}
+
+/// Returns every defined type by id, once `TypeRef`s have been resolved.
+fn definitions<'a>(root: &'a Root, context: &Context) -> HashMap<TypeId, &'a Type> {
+    root.files().iter()
+        .flat_map(|file| file.types().iter().filter_map(move |type_def| {
+            context.lookup(&file.namespace().with_appended(type_def.name())).map(|id| (id, type_def.type_()))
+        }))
+        .collect()
+}
+
+/// Builds the `Context` for `root`, links every `TypeRef` to the type it names and checks default
+/// values and constants against their types, and that map keys can be hashed.
+pub fn resolve(mut root: Root) -> Result<Root, Vec<ResolveError>> {
+    let mut context = Context::new();
+    let mut errors = vec![];
+
+    let mut consts = vec![];
+    for file in root.files() {
+        for const_def in file.consts() {
+            let name = file.namespace().with_appended(const_def.name());
+            if consts.contains(&name) {
+                errors.push(ResolveError::DuplicateConst {
+                    name,
+                    location: Location { path: file.path().clone(), span: *const_def.span() }
+                });
+            } else {
+                consts.push(name);
+            }
+        }
+        for type_def in file.types() {
+            let info = TypeInfo::new(file.namespace().clone(), type_def.name().clone(), type_def.params().len());
+            if context.add(info.clone()).is_none() {
+                errors.push(ResolveError::DuplicateType {
+                    name: info.qualified_name(),
+                    location: Location { path: file.path().clone(), span: *type_def.span() }
+                });
+            }
+        }
+    }
+
+    let no_types = HashMap::new();
+    for file in root.files_mut() {
+        let path = file.path().clone();
+        let namespace = file.namespace().clone();
+        for const_def in file.consts_mut() {
+            let name = const_def.name().clone();
+            let mut resolver = Resolver {
+                context: &context,
+                path: &path,
+                namespace: &namespace,
+                type_def: &name,
+                params: &[],
+                types: &no_types,
+                errors: vec![]
+            };
+            resolver.resolve_type_ref(const_def.type__mut());
+            errors.extend(resolver.errors);
+        }
+        for type_def in file.types_mut() {
+            let name = type_def.name().clone();
+            let params = type_def.params().clone();
+            let mut resolver = Resolver {
+                context: &context,
+                path: &path,
+                namespace: &namespace,
+                type_def: &name,
+                params: &params,
+                types: &no_types,
+                errors: vec![]
+            };
+            resolver.resolve_type_def(type_def);
+            errors.extend(resolver.errors);
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    // Aliases can only be followed once every type they may name has been resolved.
+    let types = definitions(&root, &context);
+    for file in root.files() {
+        for type_def in file.types() {
+            let mut resolver = Resolver {
+                context: &context,
+                path: file.path(),
+                namespace: file.namespace(),
+                type_def: type_def.name(),
+                params: type_def.params(),
+                types: &types,
+                errors: vec![]
+            };
+            resolver.check_cycles(type_def);
+            errors.extend(resolver.errors);
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    for file in root.files() {
+        for const_def in file.consts() {
+            let mut resolver = Resolver {
+                context: &context,
+                path: file.path(),
+                namespace: file.namespace(),
+                type_def: const_def.name(),
+                params: &[],
+                types: &types,
+                errors: vec![]
+            };
+            resolver.check_const(const_def);
+            errors.extend(resolver.errors);
+        }
+        for type_def in file.types() {
+            let mut resolver = Resolver {
+                context: &context,
+                path: file.path(),
+                namespace: file.namespace(),
+                type_def: type_def.name(),
+                params: type_def.params(),
+                types: &types,
+                errors: vec![]
+            };
+            resolver.check_fields(type_def);
+            errors.extend(resolver.errors);
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    root.set_context(context);
+    Ok(root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_ref(name: &str, params: Vec<TypeRef>) -> TypeRef {
+        TypeRef::new(QualifiedName::from(name.split('.').collect::<Vec<&str>>()), params, None, None, None)
+    }
+
+    fn type_def(name: &str, type_: Type) -> TypeDef {
+        TypeDef::new(Name::from(name), vec![], vec![], vec![], type_, None)
+    }
+
+    fn field(name: &str, type_: TypeRef, default: Option<Literal>) -> Field {
+        Field::new(Name::from(name), false, type_, default, vec![], vec![], None)
+    }
+
+    fn struct_def(name: &str, fields: Vec<Field>) -> TypeDef {
+        type_def(name, Type::Struct(Struct::new(fields)))
+    }
+
+    fn file(namespace: &str, types: Vec<TypeDef>) -> File {
+        File::new(None, false, QualifiedName::from(namespace.split('.').collect::<Vec<&str>>()), vec![], vec![], types)
+    }
+
+    fn root(types: Vec<TypeDef>) -> Root {
+        Root::new(vec![file("w", types)], Context::new())
+    }
+
+    fn errors(types: Vec<TypeDef>) -> Vec<ResolveError> {
+        resolve(root(types)).err().unwrap_or_default()
+    }
+
+    /// Returns the name of the type the first field of the first type in `namespace` resolved to.
+    fn resolved_name(root: &Root, namespace: &str) -> QualifiedName {
+        let file = root.files().iter().find(|f| f.namespace().to_string() == namespace).unwrap();
+        let Type::Struct(s) = file.types()[0].type_() else {
+            panic!("not a struct");
+        };
+        match s.fields()[0].type_().resolved() {
+            Some(ResolvedType::Defined(id)) => root.context().get(*id).unwrap().qualified_name(),
+            resolved => panic!("resolved to {:?}", resolved)
+        }
+    }
+
+    #[test]
+    fn resolves_names_relative_to_the_namespace_first() {
+        let root = Root::new(vec![
+            file("a", vec![struct_def("S", vec![field("x", type_ref("b.T", vec![]), None)])]),
+            file("a.b", vec![struct_def("T", vec![field("x", type_ref("Int32", vec![]), None)])]),
+            file("b", vec![struct_def("T", vec![field("x", type_ref("a.S", vec![]), None)])])
+        ], Context::new());
+        let root = resolve(root).unwrap();
+        assert_eq!(resolved_name(&root, "a"), QualifiedName::from(vec!["a", "b", "T"]));
+        assert_eq!(resolved_name(&root, "b"), QualifiedName::from(vec!["a", "S"]));
+    }
+
+    #[test]
+    fn rejects_unknown_types() {
+        let types = vec![struct_def("S", vec![field("x", type_ref("Missing", vec![]), None)])];
+        assert_eq!(errors(types), vec![ResolveError::UnknownType {
+            name: QualifiedName::from(vec!["Missing"]),
+            namespace: QualifiedName::from(vec!["w"]),
+            type_def: Name::from("S"),
+            location: Location::default()
+        }]);
+    }
+
+    #[test]
+    fn rejects_duplicate_types() {
+        let types = vec![struct_def("S", vec![]), struct_def("S", vec![])];
+        assert!(matches!(errors(types).as_slice(), [ResolveError::DuplicateType { .. }]));
+    }
+
+    #[test]
+    fn rejects_types_differing_in_case() {
+        let types = vec![
+            type_def("A", Type::Struct(Struct::new(vec![]))),
+            type_def("a", Type::Alias(type_ref("Int32", vec![])))
+        ];
+        assert_eq!(errors(types), vec![ResolveError::DuplicateType {
+            name: QualifiedName::from(vec!["w", "a"]),
+            location: Location::default()
+        }]);
+    }
+
+    #[test]
+    fn rejects_empty_enums() {
+        let types = vec![
+            type_def("E", Type::Enum(Enum::new(vec![]))),
+            type_def("C", Type::CEnum(CEnum::new(None, vec![])))
+        ];
+        assert!(matches!(errors(types).as_slice(), [ResolveError::InvalidEnum { .. }, ResolveError::InvalidEnum { .. }]));
+    }
+
+    #[test]
+    fn resolves_generic_params() {
+        let mut s = struct_def("Page", vec![field("items", type_ref("List", vec![type_ref("T", vec![])]), None)]);
+        s.set_params(vec![Name::from("T")]);
+        let root = resolve(root(vec![s])).unwrap();
+        let Type::Struct(s) = root.files()[0].types()[0].type_() else {
+            panic!("not a struct");
+        };
+        let items = s.fields()[0].type_();
+        assert_eq!(items.resolved(), &Some(ResolvedType::Container(Container::List)));
+        assert_eq!(items.params()[0].resolved(), &Some(ResolvedType::Param(0)));
+    }
+
+    #[test]
+    fn rejects_wrong_arity() {
+        let mut page = struct_def("Page", vec![field("items", type_ref("List", vec![type_ref("T", vec![])]), None)]);
+        page.set_params(vec![Name::from("T")]);
+        let types = vec![page, struct_def("S", vec![
+            field("a", type_ref("Page", vec![]), None),
+            field("b", type_ref("Int32", vec![type_ref("Int32", vec![])]), None)
+        ])];
+        assert_eq!(errors(types), vec![
+            ResolveError::WrongArity {
+                name: QualifiedName::from(vec!["Page"]),
+                expected: 1,
+                found: 0,
+                location: Location::default()
+            },
+            ResolveError::WrongArity {
+                name: QualifiedName::from(vec!["Int32"]),
+                expected: 0,
+                found: 1,
+                location: Location::default()
+            }
+        ]);
+    }
+
+    #[test]
+    fn checks_container_arity() {
+        let int = || type_ref("Int32", vec![]);
+        let types = vec![struct_def("S", vec![
+            field("a", type_ref("Map", vec![int(), int()]), None),
+            field("b", type_ref("Map", vec![int()]), None),
+            field("c", type_ref("Option", vec![int(), int()]), None),
+            field("d", type_ref("Set", vec![]), None)
+        ])];
+        let arities = errors(types).into_iter()
+            .map(|error| match error {
+                ResolveError::WrongArity { name, expected, found, .. } => (name.to_string(), expected, found),
+                error => panic!("unexpected error {}", error)
+            })
+            .collect::<Vec<(String, usize, usize)>>();
+        assert_eq!(arities, vec![("Map".to_string(), 2, 1), ("Option".to_string(), 1, 2), ("Set".to_string(), 1, 0)]);
+    }
+
+    #[test]
+    fn rejects_optional_variants() {
+        let mut variant = field("A", type_ref("Int32", vec![]), None);
+        variant.set_optional(true);
+        let types = vec![type_def("E", Type::Enum(Enum::new(vec![variant])))];
+        assert_eq!(errors(types), vec![ResolveError::OptionalVariant {
+            variant: Name::from("A"),
+            location: Location::default()
+        }]);
+    }
+
+    #[test]
+    fn numbers_c_enum_values() {
+        let value = |name: &str, discriminant: Option<i64>| EnumValue::new(Name::from(name), discriminant, vec![], vec![], None);
+        let e = CEnum::new(None, vec![value("A", None), value("B", Some(10)), value("C", None)]);
+        let root = resolve(root(vec![type_def("E", Type::CEnum(e))])).unwrap();
+        let Type::CEnum(e) = root.files()[0].types()[0].type_() else {
+            panic!("not a C-like enum");
+        };
+        let discriminants = e.values().iter().map(|v| v.discriminant().unwrap()).collect::<Vec<i64>>();
+        assert_eq!(discriminants, vec![0, 10, 11]);
+    }
+
+    #[test]
+    fn rejects_invalid_c_enum_values() {
+        let value = |name: &str, discriminant: Option<i64>| EnumValue::new(Name::from(name), discriminant, vec![], vec![], None);
+        let uint8 = || Some(type_ref("UInt8", vec![]));
+        let types = vec![
+            type_def("A", Type::CEnum(CEnum::new(uint8(), vec![value("X", Some(255)), value("Y", None)]))),
+            type_def("B", Type::CEnum(CEnum::new(uint8(), vec![value("X", Some(1)), value("Y", Some(1))]))),
+            type_def("C", Type::CEnum(CEnum::new(uint8(), vec![value("X", None), value("X", None)]))),
+            type_def("D", Type::CEnum(CEnum::new(Some(type_ref("String", vec![])), vec![value("X", None)]))),
+            type_def("E", Type::CEnum(CEnum::new(None, vec![value("X", Some(i64::MAX)), value("Y", None)]))),
+            type_def("F", Type::CEnum(CEnum::new(uint8(), vec![value("X", Some(-1))])))
+        ];
+        let messages = errors(types).into_iter()
+            .map(|error| match error {
+                ResolveError::InvalidEnum { type_def, message, .. } => format!("{}: {}", type_def, message),
+                error => panic!("unexpected error {}", error)
+            })
+            .collect::<Vec<String>>();
+        assert_eq!(messages, vec![
+            "A: the discriminant of \"Y\": 256 is out of range for UInt8",
+            "B: \"X\" and \"Y\" have the same discriminant 1",
+            "C: duplicate value \"X\"",
+            "D: the backing type must be an integer primitive, not String",
+            "E: the discriminant of \"Y\" overflows",
+            "F: the discriminant of \"X\": -1 is out of range for UInt8"
+        ]);
+    }
+
+    #[test]
+    fn checks_array_lengths() {
+        let uint8 = || type_ref("UInt8", vec![]);
+        let mut array = type_ref("Array", vec![uint8()]);
+        array.set_length(Some(32));
+        let mut list = type_ref("List", vec![uint8()]);
+        list.set_length(Some(3));
+        let types = vec![struct_def("S", vec![
+            field("a", array, None),
+            field("b", type_ref("Array", vec![uint8()]), None),
+            field("c", list, None)
+        ])];
+        assert_eq!(errors(types), vec![
+            ResolveError::WrongLength {
+                name: QualifiedName::from(vec!["Array"]),
+                expected: true,
+                location: Location::default()
+            },
+            ResolveError::WrongLength {
+                name: QualifiedName::from(vec!["List"]),
+                expected: false,
+                location: Location::default()
+            }
+        ]);
+    }
+
+    #[test]
+    fn rejects_unused_params() {
+        let mut s = type_def("S", Type::Struct(Struct::new(vec![
+            field("x", type_ref("List", vec![type_ref("T", vec![])]), None)
+        ])));
+        s.set_params(vec![Name::from("T"), Name::from("U")]);
+        assert_eq!(errors(vec![s]), vec![ResolveError::UnusedParam {
+            param: Name::from("U"),
+            type_def: Name::from("S"),
+            location: Location::default()
+        }]);
+    }
+
+    #[test]
+    fn rejects_unhashable_keys() {
+        let float = || type_ref("Float32", vec![]);
+        let types = vec![
+            type_def("F", Type::Newtype(type_ref("List", vec![float()]))),
+            type_def("S", Type::Struct(Struct::new(vec![
+                field("a", type_ref("Set", vec![float()]), None),
+                field("b", type_ref("Map", vec![type_ref("F", vec![]), float()]), None),
+                field("c", type_ref("Set", vec![type_ref("Set", vec![type_ref("Int32", vec![])])]), None),
+                field("d", type_ref("Map", vec![type_ref("String", vec![]), float()]), None)
+            ])))
+        ];
+        let names = errors(types).into_iter()
+            .map(|error| match error {
+                ResolveError::InvalidKey { name, .. } => name.to_string(),
+                error => panic!("unexpected error {}", error)
+            })
+            .collect::<Vec<String>>();
+        assert_eq!(names, vec!["Float32", "F", "Set"]);
+    }
+
+    #[test]
+    fn resolves_newtype_through_list() {
+        let types = vec![type_def("N", Type::Newtype(type_ref("List", vec![type_ref("N", vec![])])))];
+        assert_eq!(errors(types), vec![]);
+    }
+
+    #[test]
+    fn rejects_newtype_of_itself() {
+        let types = vec![
+            type_def("N", Type::Newtype(type_ref("N", vec![]))),
+            type_def("S", Type::Struct(Struct::new(vec![field("n", type_ref("N", vec![]), Some(Literal::Int(1)))])))
+        ];
+        assert_eq!(errors(types), vec![ResolveError::NewtypeCycle {
+            name: QualifiedName::from(vec!["w", "N"]),
+            location: Location::default()
+        }]);
+    }
+
+    #[test]
+    fn rejects_newtype_of_option_of_itself() {
+        let types = vec![type_def("N", Type::Newtype(type_ref("Option", vec![type_ref("N", vec![])])))];
+        assert!(matches!(errors(types).as_slice(), [ResolveError::NewtypeCycle { .. }]));
+    }
+
+    #[test]
+    fn rejects_alias_and_newtype_of_each_other() {
+        let types = vec![
+            type_def("A", Type::Alias(type_ref("M", vec![]))),
+            type_def("M", Type::Newtype(type_ref("A", vec![]))),
+            type_def("S", Type::Struct(Struct::new(vec![field("a", type_ref("A", vec![]), Some(Literal::Int(1)))])))
+        ];
+        assert_eq!(errors(types), vec![ResolveError::NewtypeCycle {
+            name: QualifiedName::from(vec!["w", "M"]),
+            location: Location::default()
+        }]);
+    }
+
+    #[test]
+    fn rejects_types_containing_themselves() {
+        let node = |name: &str, next: TypeRef| struct_def(name, vec![field("next", next, None)]);
+        let infinite = |name: &str| ResolveError::InfiniteType {
+            name: QualifiedName::from(vec!["w", name]),
+            location: Location::default()
+        };
+
+        let types = vec![node("Node", type_ref("Option", vec![type_ref("Node", vec![])]))];
+        assert_eq!(errors(types), vec![infinite("Node")]);
+
+        let mut array = type_ref("Array", vec![type_ref("A", vec![])]);
+        array.set_length(Some(2));
+        let types = vec![node("A", type_ref("B", vec![])), node("B", array)];
+        assert_eq!(errors(types), vec![infinite("A"), infinite("B")]);
+
+        let types = vec![type_def("E", Type::Enum(Enum::new(vec![field("e", type_ref("E", vec![]), None)])))];
+        assert_eq!(errors(types), vec![infinite("E")]);
+
+        let mut wrap = node("Wrap", type_ref("Option", vec![type_ref("T", vec![])]));
+        wrap.set_params(vec![Name::from("T")]);
+        let types = vec![wrap, node("Node", type_ref("Wrap", vec![type_ref("Node", vec![])]))];
+        assert_eq!(errors(types), vec![infinite("Node")]);
+    }
+
+    #[test]
+    fn resolves_types_containing_themselves_through_a_list() {
+        let mut page = struct_def("Page", vec![field("items", type_ref("List", vec![type_ref("T", vec![])]), None)]);
+        page.set_params(vec![Name::from("T")]);
+        let types = vec![
+            page,
+            struct_def("Node", vec![
+                field("children", type_ref("List", vec![type_ref("Node", vec![])]), None),
+                field("page", type_ref("Page", vec![type_ref("Node", vec![])]), None),
+                field("index", type_ref("Map", vec![type_ref("String", vec![]), type_ref("Node", vec![])]), None)
+            ])
+        ];
+        assert_eq!(errors(types), vec![]);
+    }
+
+    #[test]
+    fn rejects_alias_of_itself() {
+        let types = vec![type_def("A", Type::Alias(type_ref("List", vec![type_ref("A", vec![])])))];
+        assert_eq!(errors(types), vec![ResolveError::AliasCycle {
+            name: QualifiedName::from(vec!["w", "A"]),
+            location: Location::default()
+        }]);
+    }
+
+    #[test]
+    fn checks_defaults_against_field_types() {
+        let int = || type_ref("Int32", vec![]);
+        let types = vec![
+            type_def("E", Type::Enum(Enum::new(vec![field("A", type_ref("Unit", vec![]), None), field("B", int(), None)]))),
+            struct_def("S", vec![
+                field("a", int(), Some(Literal::String("x".to_string()))),
+                field("b", type_ref("UInt8", vec![]), Some(Literal::Int(256))),
+                field("c", type_ref("E", vec![]), Some(Literal::Variant(QualifiedName::from(vec!["E", "B"])))),
+                field("d", type_ref("List", vec![int()]), Some(Literal::List(vec![Literal::Int(1), Literal::Bool(true)]))),
+                field("e", type_ref("Map", vec![type_ref("String", vec![]), int()]), Some(Literal::Map(vec![
+                    (Literal::String("k".to_string()), Literal::Int(1))
+                ]))),
+                field("f", type_ref("E", vec![]), Some(Literal::Variant(QualifiedName::from(vec!["E", "A"])))),
+                field("g", type_ref("Float64", vec![]), Some(Literal::Int(1)))
+            ])
+        ];
+        let messages = errors(types).into_iter()
+            .map(|error| match error {
+                ResolveError::InvalidDefault { field, message, .. } => format!("{}: {}", field, message),
+                error => panic!("unexpected error {}", error)
+            })
+            .collect::<Vec<String>>();
+        assert_eq!(messages, vec![
+            "a: \"x\" is not a valid Int32",
+            "b: 256 is out of range for UInt8",
+            "c: \"E\" has no variant \"B\" without a value",
+            "d: true is not a valid Int32"
+        ]);
+    }
+
+    #[test]
+    fn checks_constants() {
+        let constant = |name: &str, type_: TypeRef, value: Literal| {
+            ConstDef::new(Name::from(name), type_, value, vec![], vec![], None)
+        };
+        let types = vec![type_def("Port", Type::Alias(type_ref("UInt16", vec![])))];
+        let consts = vec![
+            constant("A", type_ref("UInt8", vec![]), Literal::Int(300)),
+            constant("B", type_ref("Bool", vec![]), Literal::Int(1)),
+            constant("C", type_ref("Port", vec![]), Literal::Int(80)),
+            constant("D", type_ref("List", vec![type_ref("Int32", vec![])]), Literal::List(vec![]))
+        ];
+        let root = Root::new(vec![File::new(None, false, QualifiedName::from(vec!["w"]), vec![], consts, types)], Context::new());
+        let messages = resolve(root).unwrap_err().into_iter()
+            .map(|error| match error {
+                ResolveError::InvalidConst { name, message, .. } => format!("{}: {}", name, message),
+                error => panic!("unexpected error {}", error)
+            })
+            .collect::<Vec<String>>();
+        assert_eq!(messages, vec![
+            "A: 300 is out of range for UInt8",
+            "B: 1 is not a valid Bool",
+            "D: constants must have a primitive type, not List"
+        ]);
+    }
+
+    #[test]
+    fn rejects_duplicate_constants() {
+        let constant = || ConstDef::new(Name::from("A"), type_ref("Int32", vec![]), Literal::Int(1), vec![], vec![], None);
+        let root = Root::new(vec![File::new(None, false, QualifiedName::from(vec!["w"]), vec![], vec![constant(), constant()], vec![])], Context::new());
+        assert_eq!(resolve(root).unwrap_err(), vec![ResolveError::DuplicateConst {
+            name: QualifiedName::from(vec!["w", "A"]),
+            location: Location::default()
+        }]);
+    }
+
+    #[test]
+    fn checks_default_through_newtype() {
+        let types = vec![
+            type_def("N", Type::Newtype(type_ref("UInt8", vec![]))),
+            type_def("S", Type::Struct(Struct::new(vec![field("n", type_ref("N", vec![]), Some(Literal::Int(256)))])))
+        ];
+        assert!(matches!(errors(types).as_slice(), [ResolveError::InvalidDefault { .. }]));
+    }
+}
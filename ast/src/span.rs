@@ -0,0 +1,13 @@
+use serde::{Serialize, Deserialize};
+
+/// A location in an input file. `file` is the index of the file in `Root::files`, `offset` and `end`
+/// are byte offsets and `line`/`column` are 1-based.
+#[derive(Constructor, Clone, Copy, CopyGetters, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[get_copy="pub"]
+pub struct Span {
+    file: usize,
+    offset: usize,
+    end: usize,
+    line: usize,
+    column: usize
+}
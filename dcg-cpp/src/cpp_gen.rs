@@ -0,0 +1,552 @@
+use std::{collections::{BTreeMap, BTreeSet}, path::PathBuf};
+
+use app_common::{AppError, options::Options, protocol::OutputFile};
+use ast::*;
+
+const RESERVED: &[&str] = &[
+    "alignas", "alignof", "and", "and_eq", "asm", "auto", "bitand", "bitor", "bool", "break", "case",
+    "catch", "char", "class", "compl", "concept", "const", "consteval", "constexpr", "constinit",
+    "const_cast", "continue", "decltype", "default", "delete", "do", "double", "dynamic_cast", "else",
+    "enum", "explicit", "export", "extern", "false", "float", "for", "friend", "goto", "if", "inline",
+    "int", "long", "mutable", "namespace", "new", "noexcept", "not", "not_eq", "nullptr", "operator",
+    "or", "or_eq", "private", "protected", "public", "register", "reinterpret_cast", "requires",
+    "return", "short", "signed", "sizeof", "static", "static_assert", "static_cast", "struct",
+    "switch", "template", "this", "throw", "true", "try", "typedef", "typeid", "typename", "union",
+    "unsigned", "using", "virtual", "void", "volatile", "while", "xor", "xor_eq", "std", "value",
+    "Value"
+];
+
+/// Settings taken from the generator's options.
+pub struct Config {
+    /// The C++ standard to target, set with `std=17` or `std=20`. C++20 adds defaulted comparisons.
+    std: u32
+}
+
+impl Config {
+    pub fn from_options(options: &Options) -> Result<Config, AppError> {
+        options.check(&["std"])?;
+        let std = options.get_parsed::<u32>("std")?.unwrap_or(17);
+        if std != 17 && std != 20 {
+            return Err(AppError::Msg(format!("Unsupported C++ standard {}, expected 17 or 20.", std)));
+        }
+        Ok(Config { std })
+    }
+
+    /// Returns the comparison operator declaration for a class, if the standard allows defaulting it.
+    fn equality(&self, class: &str) -> Option<String> {
+        match self.std {
+            17 => None,
+            _ => Some(format!("bool operator==(const {}&) const = default;", class))
+        }
+    }
+}
+
+fn namespace_name(name: &Name) -> String {
+    name.to_snake_case(RESERVED)
+}
+
+fn type_name(name: &Name) -> String {
+    name.to_upper_camel_case(RESERVED)
+}
+
+fn field_name(name: &Name) -> String {
+    name.to_snake_case(RESERVED)
+}
+
+fn const_name(name: &Name) -> String {
+    name.to_screaming_snake_case(RESERVED)
+}
+
+fn header_path(namespace: &[String]) -> PathBuf {
+    let mut path = namespace.iter().collect::<PathBuf>();
+    path.set_extension("hpp");
+    path
+}
+
+fn primitive_type(primitive: &Primitive) -> &'static str {
+    match primitive {
+        Primitive::Unit => "std::monostate",
+        Primitive::Bool => "bool",
+        Primitive::Int8 => "std::int8_t",
+        Primitive::Int16 => "std::int16_t",
+        Primitive::Int32 => "std::int32_t",
+        Primitive::Int64 => "std::int64_t",
+        Primitive::UInt8 => "std::uint8_t",
+        Primitive::UInt16 => "std::uint16_t",
+        Primitive::UInt32 => "std::uint32_t",
+        Primitive::UInt64 => "std::uint64_t",
+        Primitive::Float32 => "float",
+        Primitive::Float64 => "double",
+        Primitive::String => "std::string",
+        Primitive::Bytes => "std::vector<std::uint8_t>",
+    }
+}
+
+fn container_type(container: &Container) -> &'static str {
+    match container {
+        Container::List => "std::vector",
+        Container::Map => "std::map",
+        Container::Set => "std::set",
+        Container::Option => "std::optional",
+        Container::Array => "std::array",
+    }
+}
+
+/// The namespace, and within it the template parameters of the type, that code is currently being generated for.
+struct Scope<'a> {
+    root: &'a Root,
+    config: &'a Config,
+    namespace: Vec<String>,
+    params: Vec<Name>
+}
+
+impl Scope<'_> {
+    fn with_params(&self, params: &[Name]) -> Scope<'_> {
+        Scope { root: self.root, config: self.config, namespace: self.namespace.clone(), params: params.to_vec() }
+    }
+}
+
+fn type_namespace(info: &TypeInfo) -> Vec<String> {
+    info.namespace().names().iter().map(namespace_name).collect()
+}
+
+/// Converts a type reference into a C++ type. User types are emitted fully qualified from the global
+/// namespace so that they can't be shadowed by the nested alternatives of an enum.
+fn cpp_type(type_ref: &TypeRef, scope: &Scope) -> String {
+    let params = type_ref.params().iter()
+        .map(|p| cpp_type(p, scope))
+        .chain(type_ref.length().map(|length| length.to_string()))
+        .collect::<Vec<String>>();
+
+    let path = match type_ref.resolved() {
+        Some(ResolvedType::Primitive(p)) => return primitive_type(p).to_string(),
+        Some(ResolvedType::Container(container)) => container_type(container).to_string(),
+        Some(ResolvedType::Defined(id)) => match scope.root.context().get(*id) {
+            Some(info) => {
+                let mut path = vec![String::new()];
+                path.extend(type_namespace(info));
+                path.push(type_name(info.name()));
+                path.join("::")
+            },
+            None => type_ref.name().to_string()
+        },
+        Some(ResolvedType::Param(index)) => match scope.params.get(*index) {
+            Some(param) => type_name(param),
+            None => type_ref.name().to_string()
+        },
+        None => type_ref.name().to_string()
+    };
+
+    if params.is_empty() {
+        path
+    } else {
+        format!("{}<{}>", path, params.join(", "))
+    }
+}
+
+/// Returns the type of a field, which is wrapped in `std::optional` if the field is optional.
+fn field_type(field: &Field, scope: &Scope) -> String {
+    match field.optional() {
+        true => format!("std::optional<{}>", cpp_type(field.type_(), scope)),
+        false => cpp_type(field.type_(), scope)
+    }
+}
+
+/// Returns the name of an enum alternative's struct. A nested type may not share the name of the
+/// enclosing class.
+fn alternative_name(enum_name: &str, variant: &Name) -> String {
+    let mut alternative = type_name(variant);
+    if alternative == enum_name {
+        alternative.push('_');
+    }
+    alternative
+}
+
+fn cpp_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for ch in s.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            ch if ch.is_ascii_control() => out.push_str(&format!("\\{:03o}", ch as u32)),
+            ch => out.push(ch)
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Converts a default value into an initializer of the field's type. `resolve` has already checked
+/// that it matches.
+fn cpp_literal(literal: &Literal, type_ref: &TypeRef, scope: &Scope) -> String {
+    let type_ref = scope.root.unaliased(type_ref);
+    let params = type_ref.params();
+    let join = |items: &[Literal], type_ref: &TypeRef| items.iter()
+        .map(|item| cpp_literal(item, type_ref, scope))
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    match (type_ref.resolved(), literal) {
+        (Some(ResolvedType::Primitive(Primitive::Float32)), Literal::Int(i)) => format!("{:?}f", *i as f64),
+        (Some(ResolvedType::Primitive(Primitive::Float32)), Literal::Float(x)) => format!("{:?}f", x),
+        (Some(ResolvedType::Primitive(Primitive::Float64)), Literal::Int(i)) => format!("{:?}", *i as f64),
+        // The literal -9223372036854775808 is the negation of a value too large for any integer type.
+        (Some(ResolvedType::Primitive(Primitive::Int64)), Literal::Int(i)) if *i == i64::MIN => "INT64_MIN".to_string(),
+        (Some(ResolvedType::Primitive(Primitive::Bytes)), Literal::List(items)) =>
+            format!("{{{}}}", items.iter().map(Literal::to_string).collect::<Vec<String>>().join(", ")),
+        (Some(ResolvedType::Container(Container::List | Container::Set | Container::Array)), Literal::List(items)) =>
+            format!("{{{}}}", join(items, &params[0])),
+        (Some(ResolvedType::Container(Container::Map)), Literal::Map(entries)) => {
+            let entries = entries.iter()
+                .map(|(key, value)| format!("{{{}, {}}}", cpp_literal(key, &params[0], scope), cpp_literal(value, &params[1], scope)))
+                .collect::<Vec<String>>();
+            format!("{{{}}}", entries.join(", "))
+        },
+        (Some(ResolvedType::Container(Container::Option)), _) => cpp_optional_literal(literal, &params[0], scope),
+        (Some(ResolvedType::Defined(id)), _) => match scope.root.type_def(*id) {
+            Some(type_def) => cpp_defined_literal(literal, type_ref, type_def, scope),
+            None => literal.to_string()
+        },
+        (_, Literal::String(s)) => cpp_string(s),
+        _ => literal.to_string()
+    }
+}
+
+/// Converts a default value of a user-defined type: the value of a newtype or a variant of an enum.
+fn cpp_defined_literal(literal: &Literal, type_ref: &TypeRef, type_def: &TypeDef, scope: &Scope) -> String {
+    let class = cpp_type(type_ref, scope);
+    let variant = match literal {
+        Literal::Variant(name) => name.last(),
+        _ => None
+    };
+    match (type_def.type_(), variant) {
+        (Type::Newtype(target), _) => format!("{}{{{}}}", class, cpp_literal(literal, target, scope)),
+        (Type::CEnum(_), Some(variant)) => format!("{}::{}", class, type_name(variant)),
+        (_, Some(variant)) => format!("{}{{{}::{}{{}}}}", class, class, alternative_name(&type_name(type_def.name()), variant)),
+        (_, None) => literal.to_string()
+    }
+}
+
+/// Converts a default value into an initializer of a `std::optional` containing `type_ref`. A braced
+/// initializer can't convert to a `std::optional`, so the contained type is named.
+fn cpp_optional_literal(literal: &Literal, type_ref: &TypeRef, scope: &Scope) -> String {
+    match scope.root.unaliased(type_ref).resolved() {
+        Some(ResolvedType::Primitive(Primitive::Bytes) | ResolvedType::Container(Container::List | Container::Set | Container::Map | Container::Array)) =>
+            format!("{}{}", cpp_type(type_ref, scope), cpp_literal(literal, type_ref, scope)),
+        _ => cpp_literal(literal, type_ref, scope)
+    }
+}
+
+/// Collects every defined type reachable from `type_ref`.
+fn referenced_types<'a>(type_ref: &TypeRef, scope: &Scope<'a>, types: &mut Vec<&'a TypeInfo>) {
+    if let Some(ResolvedType::Defined(id)) = type_ref.resolved() {
+        types.extend(scope.root.context().get(*id));
+    }
+    for param in type_ref.params() {
+        referenced_types(param, scope, types);
+    }
+}
+
+fn referenced_namespaces(type_refs: &[&TypeRef], scope: &Scope) -> BTreeSet<Vec<String>> {
+    let mut types = vec![];
+    for type_ref in type_refs {
+        referenced_types(type_ref, scope, &mut types);
+    }
+    types.into_iter().map(type_namespace).collect()
+}
+
+/// Returns the names of the types in the current namespace that `type_def` uses.
+fn referenced_local_types(type_def: &TypeDef, scope: &Scope) -> BTreeSet<String> {
+    let mut types = vec![];
+    for type_ref in type_refs(type_def) {
+        referenced_types(type_ref, scope, &mut types);
+    }
+    types.into_iter()
+        .filter(|info| type_namespace(info) == scope.namespace)
+        .map(|info| type_name(info.name()))
+        .collect()
+}
+
+/// Returns the types that the definition of `type_def` refers to.
+fn type_refs(type_def: &TypeDef) -> Vec<&TypeRef> {
+    match type_def.type_() {
+        Type::Struct(s) => s.fields().iter().map(Field::type_).collect(),
+        Type::Enum(e) => e.fields().iter().map(Field::type_).collect(),
+        Type::Alias(target) | Type::Newtype(target) => vec![target],
+        Type::Primitive(_) | Type::CEnum(_) => vec![]
+    }
+}
+
+/// Orders the types of a namespace so that each type is declared after the local types it uses.
+/// Types that take part in a cycle keep their source order.
+fn sort_types<'a>(types: &[&'a TypeDef], scope: &Scope) -> Vec<&'a TypeDef> {
+    let names = types.iter().map(|t| type_name(t.name())).collect::<Vec<String>>();
+    let mut emitted = vec![false; types.len()];
+    let mut sorted = vec![];
+
+    while sorted.len() < types.len() {
+        let ready = (0..types.len()).find(|&i| {
+            if emitted[i] {
+                return false;
+            }
+            referenced_local_types(types[i], scope).iter().all(|dep| {
+                names.iter().enumerate().all(|(j, name)| j == i || name != dep || emitted[j])
+            })
+        });
+
+        let next = ready.unwrap_or_else(|| emitted.iter().position(|e| !e).unwrap());
+        emitted[next] = true;
+        sorted.push(types[next]);
+    }
+
+    sorted
+}
+
+/// Returns the template declaration for a type, e.g. `template <typename T>`, if it has parameters.
+fn template(params: &[Name]) -> String {
+    if params.is_empty() {
+        return String::new();
+    }
+    let params = params.iter()
+        .map(|p| format!("typename {}", type_name(p)))
+        .collect::<Vec<String>>();
+    format!("template <{}>\n", params.join(", "))
+}
+
+/// Returns the lines of a Doxygen comment.
+fn doc_comment(doc: &[String], indent: &str) -> String {
+    doc.iter()
+        .map(|line| match line.is_empty() {
+            true => format!("{}///\n", indent),
+            false => format!("{}/// {}\n", indent, line)
+        })
+        .collect()
+}
+
+/// Returns the attribute for `@deprecated(note="...")`, followed by a space. Enum alternatives aren't
+/// marked as the enum's own `std::variant` would trigger the warning.
+fn deprecated(annotations: &[Annotation]) -> String {
+    match annotations.iter().find(|a| a.name() == "deprecated") {
+        Some(deprecated) => match deprecated.string_arg("note") {
+            Some(note) => format!("[[deprecated({})]] ", cpp_string(note)),
+            None => "[[deprecated]] ".to_string()
+        },
+        None => String::new()
+    }
+}
+
+fn generate_struct(out: &mut String, type_def: &TypeDef, s: &Struct, scope: &Scope) {
+    let name = type_def.name();
+    // The implicit constructor uses the initializers of deprecated members, which GCC and Clang warn
+    // about on every construction. Uses of the members are still warned about.
+    let suppress = s.fields().iter().any(|f| f.default().is_some() && !deprecated(f.annotations()).is_empty());
+    if suppress {
+        out.push_str("#if defined(__GNUC__)\n#pragma GCC diagnostic push\n#pragma GCC diagnostic ignored \"-Wdeprecated-declarations\"\n#endif\n");
+    }
+    out.push_str(&doc_comment(type_def.doc(), ""));
+    out.push_str(&template(&scope.params));
+    out.push_str(&format!("class {}{} {{\npublic:\n", deprecated(type_def.annotations()), type_name(name)));
+    for field in s.fields() {
+        out.push_str(&doc_comment(field.doc(), "    "));
+        match field.default() {
+            Some(default) => out.push_str(&format!(
+                "    {}{} {} = {};\n",
+                deprecated(field.annotations()),
+                field_type(field, scope),
+                field_name(field.name()),
+                match field.optional() {
+                    true => cpp_optional_literal(default, field.type_(), scope),
+                    false => cpp_literal(default, field.type_(), scope)
+                }
+            )),
+            None => out.push_str(&format!(
+                "    {}{} {};\n",
+                deprecated(field.annotations()),
+                field_type(field, scope),
+                field_name(field.name())
+            ))
+        }
+    }
+    if let Some(equality) = scope.config.equality(&type_name(name)) {
+        out.push_str(&format!("\n    {}\n", equality));
+    }
+    out.push_str("};\n");
+    if suppress {
+        out.push_str("#if defined(__GNUC__)\n#pragma GCC diagnostic pop\n#endif\n");
+    }
+}
+
+fn generate_enum(out: &mut String, type_def: &TypeDef, e: &Enum, scope: &Scope) {
+    let name = type_name(type_def.name());
+    out.push_str(&doc_comment(type_def.doc(), ""));
+    out.push_str(&template(&scope.params));
+    out.push_str(&format!("class {}{} {{\npublic:\n", deprecated(type_def.annotations()), name));
+
+    let mut alternatives = vec![];
+    for field in e.fields() {
+        let alternative = alternative_name(&name, field.name());
+        out.push_str(&doc_comment(field.doc(), "    "));
+        let mut members = match field.type_().primitive() {
+            Some(Primitive::Unit) => vec![],
+            _ => vec![format!("{} value;", cpp_type(field.type_(), scope))]
+        };
+        members.extend(scope.config.equality(&alternative));
+        if members.is_empty() {
+            out.push_str(&format!("    struct {} {{}};\n", alternative));
+        } else {
+            out.push_str(&format!("    struct {} {{ {} }};\n", alternative, members.join(" ")));
+        }
+        alternatives.push(alternative);
+    }
+
+    out.push_str(&format!("\n    using Value = std::variant<{}>;\n", alternatives.join(", ")));
+    out.push_str("    Value value;\n");
+    if let Some(equality) = scope.config.equality(&name) {
+        out.push_str(&format!("\n    {}\n", equality));
+    }
+    out.push_str("};\n");
+}
+
+fn generate_c_enum(out: &mut String, type_def: &TypeDef, e: &CEnum) {
+    let backing = e.backing().as_ref().and_then(TypeRef::primitive).unwrap_or(Primitive::Int32);
+    out.push_str(&doc_comment(type_def.doc(), ""));
+    out.push_str(&format!(
+        "enum class {}{} : {} {{\n",
+        deprecated(type_def.annotations()),
+        type_name(type_def.name()),
+        primitive_type(&backing)
+    ));
+    for value in e.values() {
+        out.push_str(&doc_comment(value.doc(), "    "));
+        // The attribute of an enumerator goes after its name.
+        let name = format!("{} {}", type_name(value.name()), deprecated(value.annotations()));
+        match value.discriminant() {
+            Some(i64::MIN) => out.push_str(&format!("    {}= INT64_MIN,\n", name)),
+            Some(discriminant) => out.push_str(&format!("    {}= {},\n", name, discriminant)),
+            None => out.push_str(&format!("    {},\n", name.trim_end()))
+        }
+    }
+    out.push_str("};\n");
+}
+
+fn generate_alias(out: &mut String, type_def: &TypeDef, target: &TypeRef, scope: &Scope) {
+    out.push_str(&doc_comment(type_def.doc(), ""));
+    // The attribute of an alias goes after its name.
+    out.push_str(&format!(
+        "using {} {}= {};\n",
+        type_name(type_def.name()),
+        deprecated(type_def.annotations()),
+        cpp_type(target, scope)
+    ));
+}
+
+/// A newtype is a class holding its `value`, so that it is a distinct type.
+fn generate_newtype(out: &mut String, type_def: &TypeDef, target: &TypeRef, scope: &Scope) {
+    let name = type_name(type_def.name());
+    out.push_str(&doc_comment(type_def.doc(), ""));
+    out.push_str(&format!("class {}{} {{\npublic:\n", deprecated(type_def.annotations()), name));
+    out.push_str(&format!("    {} value;\n", cpp_type(target, scope)));
+    if let Some(equality) = scope.config.equality(&name) {
+        out.push_str(&format!("\n    {}\n", equality));
+    }
+    out.push_str("};\n");
+}
+
+/// Everything declared in one namespace, across all files.
+#[derive(Default)]
+struct Namespace<'a> {
+    consts: Vec<&'a ConstDef>,
+    types: Vec<&'a TypeDef>
+}
+
+/// String constants are `std::string_view` as a `std::string` can't be `constexpr` before C++20.
+fn generate_const(out: &mut String, const_def: &ConstDef, scope: &Scope) {
+    let type_ = match scope.root.unaliased(const_def.type_()).primitive() {
+        Some(Primitive::String) => "std::string_view".to_string(),
+        _ => cpp_type(const_def.type_(), scope)
+    };
+    out.push_str(&doc_comment(const_def.doc(), ""));
+    out.push_str(&format!(
+        "{}inline constexpr {} {} = {};\n",
+        deprecated(const_def.annotations()),
+        type_,
+        const_name(const_def.name()),
+        cpp_literal(const_def.value(), const_def.type_(), scope)
+    ));
+}
+
+fn generate_header(namespace: &Namespace, scope: &Scope) -> String {
+    let types = &namespace.types;
+    let mut out = String::from("// Generated by dcg-cpp. Do not edit.\n#pragma once\n\n");
+
+    out.push_str("#include <array>\n#include <cstdint>\n#include <map>\n#include <optional>\n#include <set>\n#include <string>\n#include <string_view>\n#include <variant>\n#include <vector>\n");
+
+    let mut includes = BTreeSet::new();
+    for type_def in types {
+        includes.extend(referenced_namespaces(&type_refs(type_def), scope));
+    }
+    for const_def in &namespace.consts {
+        includes.extend(referenced_namespaces(&[const_def.type_()], scope));
+    }
+    includes.remove(&scope.namespace);
+    if !includes.is_empty() {
+        out.push('\n');
+    }
+    for include in &includes {
+        let include = header_path(include);
+        out.push_str(&format!("#include \"{}\"\n", include.to_string_lossy().replace('\\', "/")));
+    }
+
+    out.push('\n');
+    for name in &scope.namespace {
+        out.push_str(&format!("namespace {} {{\n", name));
+    }
+
+    for type_def in sort_types(types, scope) {
+        out.push('\n');
+        let scope = scope.with_params(type_def.params());
+        match type_def.type_() {
+            Type::Struct(s) => generate_struct(&mut out, type_def, s, &scope),
+            Type::Enum(e) => generate_enum(&mut out, type_def, e, &scope),
+            Type::CEnum(e) => generate_c_enum(&mut out, type_def, e),
+            Type::Alias(target) => generate_alias(&mut out, type_def, target, &scope),
+            Type::Newtype(target) => generate_newtype(&mut out, type_def, target, &scope),
+            Type::Primitive(_) => {}
+        }
+    }
+
+    // Constants come last as their type may be an alias declared above.
+    if !namespace.consts.is_empty() {
+        out.push('\n');
+    }
+    for const_def in &namespace.consts {
+        generate_const(&mut out, const_def, scope);
+    }
+
+    out.push('\n');
+    for name in scope.namespace.iter().rev() {
+        out.push_str(&format!("}} // namespace {}\n", name));
+    }
+
+    out
+}
+
+/// Generates one header per namespace.
+pub fn generate(root: &Root, config: &Config) -> Vec<OutputFile> {
+    let mut namespaces: BTreeMap<Vec<String>, Namespace> = BTreeMap::new();
+    for file in root.files().iter().filter(|f| !f.external()) {
+        let entry = namespaces.entry(file.namespace().names().iter().map(namespace_name).collect()).or_default();
+        entry.consts.extend(file.consts().iter());
+        entry.types.extend(file.types().iter());
+    }
+
+    namespaces.iter()
+        .map(|(namespace, declarations)| OutputFile {
+            path: header_path(namespace),
+            contents: generate_header(declarations, &Scope { root, config, namespace: namespace.clone(), params: vec![] })
+        })
+        .collect()
+}
@@ -0,0 +1,18 @@
+mod cpp_gen;
+
+use app_common::{AppError, CodeGenerator, options::Options, protocol::{Feature, Response}};
+use ast::Root;
+
+/// Generates one C++ header per namespace.
+pub struct CppGenerator;
+
+impl CodeGenerator for CppGenerator {
+    fn features(&self) -> &[Feature] {
+        &[Feature::Generics, Feature::Map, Feature::Set, Feature::Option, Feature::Defaults, Feature::OptionalFields, Feature::Constants, Feature::CLikeEnums, Feature::TypeAliases, Feature::Newtypes, Feature::Bytes, Feature::Arrays]
+    }
+
+    fn generate(&self, ast: &Root, options: &Options) -> Result<Response, AppError> {
+        let config = cpp_gen::Config::from_options(options)?;
+        Ok(Response::from_files(cpp_gen::generate(ast, &config)))
+    }
+}
@@ -0,0 +1,278 @@
+use std::{collections::BTreeMap, path::{Path, PathBuf}};
+
+use app_common::{CodeGenerator, options::Options};
+use dcg_cpp::CppGenerator;
+
+/// Generates C++ from one source file and returns the contents of every header by path.
+fn generate(source: &str) -> BTreeMap<PathBuf, String> {
+    let root = parser::parse(&[(PathBuf::from("test.dcg"), source.to_string())]).expect("parse failed");
+    let root = ast::resolve(root).expect("resolve failed");
+    let response = CppGenerator.generate(&root, &Options::default()).expect("generate failed");
+    response.files.into_iter().map(|file| (file.path, file.contents)).collect()
+}
+
+/// Returns the generated code after the includes.
+fn body(source: &str) -> String {
+    let files = generate(source);
+    let header = files.values().next().expect("no header");
+    header[header.find("namespace").expect("no namespace")..].to_string()
+}
+
+#[test]
+fn suppresses_deprecation_of_initialized_members() {
+    let source = "namespace app\nstruct S {\n @deprecated(note=\"old\")\n x: Int32 = 1\n y: Int32\n}\n";
+    assert_eq!(body(source), "namespace app {
+
+#if defined(__GNUC__)
+#pragma GCC diagnostic push
+#pragma GCC diagnostic ignored \"-Wdeprecated-declarations\"
+#endif
+class S {
+public:
+    [[deprecated(\"old\")]] std::int32_t x = 1;
+    std::int32_t y;
+};
+#if defined(__GNUC__)
+#pragma GCC diagnostic pop
+#endif
+
+} // namespace app
+");
+}
+
+#[test]
+fn keeps_deprecation_of_uninitialized_members() {
+    let source = "namespace app\nstruct S {\n @deprecated\n x: Int32\n}\n";
+    assert_eq!(body(source), "namespace app {
+
+class S {
+public:
+    [[deprecated]] std::int32_t x;
+};
+
+} // namespace app
+");
+}
+
+#[test]
+fn generates_classes_and_variants() {
+    let source = "namespace app.model\nstruct Point { x: Float64\n y: Float64 }\nenum Shape { Circle: Float64\n Empty: Unit\n Poly: List<Point> }\n";
+    let files = generate(source);
+    assert_eq!(files.keys().collect::<Vec<_>>(), vec![&PathBuf::from("app/model.hpp")]);
+    assert_eq!(body(source), "namespace app {
+namespace model {
+
+class Point {
+public:
+    double x;
+    double y;
+};
+
+class Shape {
+public:
+    struct Circle { double value; };
+    struct Empty {};
+    struct Poly { std::vector<::app::model::Point> value; };
+
+    using Value = std::variant<Circle, Empty, Poly>;
+    Value value;
+};
+
+} // namespace model
+} // namespace app
+");
+}
+
+#[test]
+fn includes_headers_of_other_namespaces() {
+    let root = parser::parse(&[
+        (PathBuf::from("a.dcg"), "namespace a\nstruct X { y: b.Y }\n".to_string()),
+        (PathBuf::from("b.dcg"), "namespace b\nstruct Y { x: Int32 }\n".to_string())
+    ]).expect("parse failed");
+    let root = ast::resolve(root).expect("resolve failed");
+    let response = CppGenerator.generate(&root, &Options::default()).expect("generate failed");
+    let a = response.files.iter().find(|file| file.path == Path::new("a.hpp")).expect("no a.hpp");
+    assert!(a.contents.contains("#include \"b.hpp\""));
+    assert!(a.contents.contains("::b::Y y;"));
+}
+
+#[test]
+fn generates_containers() {
+    let source = "namespace app\nstruct C { m: Map<String Int32>\n s: Set<String>\n o: Option<Int64>\n l: List<Bool> }\n";
+    assert_eq!(body(source), "namespace app {
+
+class C {
+public:
+    std::map<std::string, std::int32_t> m;
+    std::set<std::string> s;
+    std::optional<std::int64_t> o;
+    std::vector<bool> l;
+};
+
+} // namespace app
+");
+}
+
+#[test]
+fn initializes_members_with_defaults() {
+    let source = "namespace app\nenum Color : UInt8 { Red = 1\n Green = 2 }\nstruct S { n: Int32 = 5\n name: String = \"x\"\n tags: List<String> = [\"a\" \"b\"]\n m: Map<String Int32> = {\"k\": 1}\n color: Color = Color.Green }\n";
+    let body = body(source);
+    assert_eq!(&body[body.find("class S").unwrap()..], "class S {
+public:
+    std::int32_t n = 5;
+    std::string name = \"x\";
+    std::vector<std::string> tags = {\"a\", \"b\"};
+    std::map<std::string, std::int32_t> m = {{\"k\", 1}};
+    ::app::Color color = ::app::Color::Green;
+};
+
+} // namespace app
+");
+}
+
+#[test]
+fn escapes_strings() {
+    let body = body("namespace app\nstruct S { name: String = \"a\\\"b\\\\c\\n\" }\n");
+    assert!(body.contains("    std::string name = \"a\\\"b\\\\c\\n\";\n"));
+}
+
+#[test]
+fn generates_optional_fields() {
+    assert_eq!(body("namespace app\nstruct S { nick?: String\n age?: Int32 = 3 }\n"), "namespace app {
+
+class S {
+public:
+    std::optional<std::string> nick;
+    std::optional<std::int32_t> age = 3;
+};
+
+} // namespace app
+");
+}
+
+#[test]
+fn generates_deprecated_classes() {
+    let source = "namespace app\n@deprecated(note=\"use V2\")\nstruct S {\n @json(name=\"user_id\")\n @deprecated\n id: Int32\n}\n";
+    assert_eq!(body(source), "namespace app {
+
+class [[deprecated(\"use V2\")]] S {
+public:
+    [[deprecated]] std::int32_t id;
+};
+
+} // namespace app
+");
+}
+
+#[test]
+fn generates_doc_comments() {
+    let source = "namespace app\n/// A user.\n/// Second line.\nstruct User {\n /// The id.\n id: Int32\n}\n/// Kinds.\nenum Kind {\n /// First.\n A: Unit\n}\n/// Colors.\nenum Color : UInt8 {\n /// Red.\n Red = 1\n}\n";
+    assert_eq!(body(source), "namespace app {
+
+/// A user.
+/// Second line.
+class User {
+public:
+    /// The id.
+    std::int32_t id;
+};
+
+/// Kinds.
+class Kind {
+public:
+    /// First.
+    struct A {};
+
+    using Value = std::variant<A>;
+    Value value;
+};
+
+/// Colors.
+enum class Color : std::uint8_t {
+    /// Red.
+    Red = 1,
+};
+
+} // namespace app
+");
+}
+
+#[test]
+fn generates_constants() {
+    let source = "namespace app\n/// Max.\nconst MAX: UInt8 = 10\nconst NAME: String = \"n\"\nconst RATIO: Float64 = 0.5\ntype Port = UInt16\nconst PORT: Port = 80\n";
+    assert_eq!(body(source), "namespace app {
+
+using Port = std::uint16_t;
+
+/// Max.
+inline constexpr std::uint8_t MAX = 10;
+inline constexpr std::string_view NAME = \"n\";
+inline constexpr double RATIO = 0.5;
+inline constexpr ::app::Port PORT = 80;
+
+} // namespace app
+");
+}
+
+#[test]
+fn generates_c_enums() {
+    assert_eq!(body("namespace app\nenum Flags : UInt16 { A = 1\n B\n C = 10\n D }\nenum Plain { X, Y }\n"), "namespace app {
+
+enum class Flags : std::uint16_t {
+    A = 1,
+    B = 2,
+    C = 10,
+    D = 11,
+};
+
+enum class Plain : std::int32_t {
+    X = 0,
+    Y = 1,
+};
+
+} // namespace app
+");
+}
+
+#[test]
+fn generates_aliases_and_newtypes() {
+    let source = "namespace app\ntype Tags = List<String>\nnewtype UserId = Int64\nnewtype Tree = List<Tree>\nstruct User { id: UserId = 7\n tags: Tags = [\"a\"] }\n";
+    assert_eq!(body(source), "namespace app {
+
+using Tags = std::vector<std::string>;
+
+class UserId {
+public:
+    std::int64_t value;
+};
+
+class Tree {
+public:
+    std::vector<::app::Tree> value;
+};
+
+class User {
+public:
+    ::app::UserId id = ::app::UserId{7};
+    ::app::Tags tags = {\"a\"};
+};
+
+} // namespace app
+");
+}
+
+#[test]
+fn generates_arrays_and_bytes() {
+    let source = "namespace app\nstruct S { hash: Array<UInt8 32>\n data: Bytes\n grid: Array<Array<Int32 3> 2> }\n";
+    assert_eq!(body(source), "namespace app {
+
+class S {
+public:
+    std::array<std::uint8_t, 32> hash;
+    std::vector<std::uint8_t> data;
+    std::array<std::array<std::int32_t, 3>, 2> grid;
+};
+
+} // namespace app
+");
+}
@@ -0,0 +1,18 @@
+mod rust_gen;
+
+use app_common::{AppError, CodeGenerator, options::Options, protocol::{Feature, Response}};
+use ast::Root;
+
+/// Generates Rust modules.
+pub struct RustGenerator;
+
+impl CodeGenerator for RustGenerator {
+    fn features(&self) -> &[Feature] {
+        &[Feature::Generics, Feature::Map, Feature::Set, Feature::Option, Feature::Defaults, Feature::OptionalFields, Feature::Constants, Feature::CLikeEnums, Feature::TypeAliases, Feature::Newtypes, Feature::Bytes, Feature::Arrays]
+    }
+
+    fn generate(&self, ast: &Root, options: &Options) -> Result<Response, AppError> {
+        let config = rust_gen::Config::from_options(options)?;
+        Ok(rust_gen::generate(ast, &config))
+    }
+}
@@ -1,16 +1,3 @@
-use app_common::AppError;
-
-fn app() -> Result<(), AppError> {
-    app_common::get_ast("dcg-rust", "DataClass Rust Generator")?;
-    Ok(())
-}
-
 fn main() {
-    match app() {
-        Ok(_) => {},
-        Err(e) => {
-            println!("Error: {:?}", e);
-            std::process::exit(1);
-        }
-    }
-}
\ No newline at end of file
+    app_common::run("dcg-rust", "DataClass Rust Generator", &dcg_rust::RustGenerator);
+}
@@ -0,0 +1,649 @@
+use std::{collections::{BTreeMap, HashSet}, path::PathBuf};
+
+use app_common::{AppError, options::Options, protocol::{Diagnostic, OutputFile, Response, Severity}};
+use ast::*;
+
+const RESERVED: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type", "unsafe",
+    "use", "where", "while", "abstract", "become", "box", "do", "final", "macro", "override", "priv",
+    "typeof", "unsized", "virtual", "yield", "try"
+];
+
+const DERIVES: &[&str] = &["Clone", "Debug", "PartialEq"];
+
+/// Traits a type needs to be a `HashMap` key or a `HashSet` value.
+const HASH_DERIVES: &[&str] = &["PartialEq", "Eq", "Hash"];
+
+/// Settings taken from the generator's options.
+pub struct Config {
+    /// Traits derived by every generated type. Set with `derive=Clone,Debug`.
+    derives: Vec<String>,
+
+    /// The module that types of imported files were generated into, e.g. `external=crate::schema`.
+    /// Without it they're expected in the same module tree, which declares their modules, so all
+    /// files should be generated in one run.
+    external: Option<String>
+}
+
+impl Config {
+    pub fn from_options(options: &Options) -> Result<Config, AppError> {
+        options.check(&["derive", "external"])?;
+        Ok(Config {
+            derives: options.get_list("derive").unwrap_or_else(|| DERIVES.iter().map(|d| d.to_string()).collect()),
+            external: options.get("external").map(str::to_string)
+        })
+    }
+
+    /// Derives the configured traits and any added to the type with `@rust(derive="Hash, Eq")`, and
+    /// those of `HASH_DERIVES` if the type is `hashed`.
+    fn derive_attribute(&self, type_def: &TypeDef, hashed: bool) -> String {
+        let mut derives = self.derives.clone();
+        let extra = type_def.annotation("rust").and_then(|a| a.string_arg("derive")).unwrap_or_default();
+        let hash_derives = HASH_DERIVES.iter().copied().filter(|_| hashed);
+        for derive in extra.split(',').map(str::trim).filter(|d| !d.is_empty()).chain(hash_derives) {
+            // `std::hash::Hash` derives `Hash`.
+            let trait_name = derive.rsplit("::").next().unwrap_or(derive);
+            if !derives.iter().any(|d| d == derive || d.rsplit("::").next() == Some(trait_name)) {
+                derives.push(derive.to_string());
+            }
+        }
+
+        if derives.is_empty() {
+            return String::new();
+        }
+        format!("#[derive({})]\n", derives.join(", "))
+    }
+
+    /// Whether serde attributes may be emitted, i.e. serde's traits are derived.
+    fn uses_serde(&self) -> bool {
+        self.derives.iter().any(|d| d.ends_with("Serialize") || d.ends_with("Deserialize"))
+    }
+}
+
+/// A node in the generated module tree. Each namespace component becomes one module.
+#[derive(Default)]
+struct Module<'a> {
+    children: BTreeMap<String, Module<'a>>,
+    consts: Vec<&'a ConstDef>,
+    types: Vec<&'a TypeDef>,
+
+    /// The namespace of the files whose types are generated into the module.
+    namespace: Option<&'a QualifiedName>,
+
+    /// Whether a file is written for the module. Modules of external files are only declared.
+    generated: bool
+}
+
+impl<'a> Module<'a> {
+    fn insert(&mut self, namespace: &[Name], file: &'a File) {
+        self.generated |= !file.external();
+        match namespace.split_first() {
+            Some((head, tail)) => self.children
+                .entry(module_name(head))
+                .or_default()
+                .insert(tail, file),
+            None if !file.external() => {
+                self.namespace = Some(file.namespace());
+                self.consts.extend(file.consts().iter());
+                self.types.extend(file.types().iter());
+            },
+            None => {}
+        }
+    }
+}
+
+fn module_name(name: &Name) -> String {
+    name.to_snake_case(RESERVED)
+}
+
+fn type_name(name: &Name) -> String {
+    name.to_upper_camel_case(RESERVED)
+}
+
+fn field_name(name: &Name) -> String {
+    name.to_snake_case(RESERVED)
+}
+
+fn const_name(name: &Name) -> String {
+    name.to_screaming_snake_case(RESERVED)
+}
+
+fn primitive_type(primitive: &Primitive) -> &'static str {
+    match primitive {
+        Primitive::Unit => "()",
+        Primitive::Bool => "bool",
+        Primitive::Int8 => "i8",
+        Primitive::Int16 => "i16",
+        Primitive::Int32 => "i32",
+        Primitive::Int64 => "i64",
+        Primitive::UInt8 => "u8",
+        Primitive::UInt16 => "u16",
+        Primitive::UInt32 => "u32",
+        Primitive::UInt64 => "u64",
+        Primitive::Float32 => "f32",
+        Primitive::Float64 => "f64",
+        Primitive::String => "String",
+        Primitive::Bytes => "Vec<u8>",
+    }
+}
+
+/// Returns the type of a container given the types of its parameters.
+fn container_type(container: &Container, params: &[String], length: u64) -> String {
+    let params = params.join(", ");
+    match container {
+        Container::List => format!("Vec<{}>", params),
+        Container::Map => format!("std::collections::HashMap<{}>", params),
+        Container::Set => format!("std::collections::HashSet<{}>", params),
+        Container::Option => format!("Option<{}>", params),
+        Container::Array => format!("[{}; {}]", params, length)
+    }
+}
+
+/// The module, and within it the generic parameters of the type, that code is currently being generated for.
+struct Scope<'a> {
+    root: &'a Root,
+    config: &'a Config,
+
+    /// The structs that get a `Default` impl, see `defaulted_structs`.
+    defaulted: &'a HashSet<TypeId>,
+    hashed: &'a Hashed,
+    path: Vec<String>,
+    params: Vec<Name>,
+
+    /// The generic parameters in `params` that must implement `Eq` and `Hash`.
+    hashed_params: Vec<bool>
+}
+
+impl Scope<'_> {
+    fn child(&self, name: &str) -> Scope<'_> {
+        let mut path = self.path.clone();
+        path.push(name.to_string());
+        Scope {
+            root: self.root,
+            config: self.config,
+            defaulted: self.defaulted,
+            hashed: self.hashed,
+            path,
+            params: vec![],
+            hashed_params: vec![]
+        }
+    }
+
+    /// Returns the scope of the type `id`, which has generic parameters `params`.
+    fn with_params(&self, id: Option<TypeId>, params: &[Name]) -> Scope<'_> {
+        Scope {
+            root: self.root,
+            config: self.config,
+            defaulted: self.defaulted,
+            hashed: self.hashed,
+            path: self.path.clone(),
+            params: params.to_vec(),
+            hashed_params: (0..params.len())
+                .map(|index| id.is_some_and(|id| self.hashed.params.contains(&(id, index))))
+                .collect()
+        }
+    }
+
+    /// Returns the path to a defined type relative to this module, or within `Config::external`
+    /// for a type of an external file.
+    fn path_to(&self, info: &TypeInfo) -> String {
+        let namespace = info.namespace().names().iter().map(module_name).collect::<Vec<String>>();
+        let external = self.root.files().iter().any(|file| *file.external() && file.namespace() == info.namespace());
+        if let (true, Some(external)) = (external, &self.config.external) {
+            let mut path = vec![external.clone()];
+            path.extend(namespace);
+            path.push(type_name(info.name()));
+            return path.join("::");
+        }
+        if namespace == self.path {
+            return type_name(info.name());
+        }
+
+        let mut path = vec!["super".to_string(); self.path.len()];
+        path.extend(namespace);
+        path.push(type_name(info.name()));
+        path.join("::")
+    }
+}
+
+fn rust_type(type_ref: &TypeRef, scope: &Scope) -> String {
+    let params = type_ref.params().iter()
+        .map(|p| rust_type(p, scope))
+        .collect::<Vec<String>>();
+
+    let path = match type_ref.resolved() {
+        Some(ResolvedType::Primitive(p)) => return primitive_type(p).to_string(),
+        Some(ResolvedType::Container(container)) => return container_type(container, &params, type_ref.length().unwrap_or_default()),
+        Some(ResolvedType::Defined(id)) => match scope.root.context().get(*id) {
+            Some(info) => scope.path_to(info),
+            None => type_ref.name().to_string()
+        },
+        Some(ResolvedType::Param(index)) => match scope.params.get(*index) {
+            Some(param) => type_name(param),
+            None => type_ref.name().to_string()
+        },
+        None => type_ref.name().to_string()
+    };
+
+    if params.is_empty() {
+        path
+    } else {
+        format!("{}<{}>", path, params.join(", "))
+    }
+}
+
+/// Returns the lines of a rustdoc comment.
+fn doc_comment(doc: &[String], indent: &str) -> String {
+    doc.iter()
+        .map(|line| match line.is_empty() {
+            true => format!("{}///\n", indent),
+            false => format!("{}/// {}\n", indent, line)
+        })
+        .collect()
+}
+
+/// Returns the attribute for `@deprecated(note="...")`, if present.
+fn deprecated_attribute(annotations: &[Annotation], indent: &str) -> String {
+    match annotations.iter().find(|a| a.name() == "deprecated") {
+        Some(deprecated) => match deprecated.string_arg("note") {
+            Some(note) => format!("{}#[deprecated(note = {:?})]\n", indent, note),
+            None => format!("{}#[deprecated]\n", indent)
+        },
+        None => String::new()
+    }
+}
+
+/// Returns the attributes for the annotations of a type, field or variant: `@deprecated(note="...")`
+/// and, if serde is derived, `@json(name="...")`.
+fn attributes(annotations: &[Annotation], config: &Config, indent: &str) -> String {
+    let mut out = deprecated_attribute(annotations, indent);
+    if config.uses_serde() {
+        if let Some(name) = annotations.iter().find(|a| a.name() == "json").and_then(|a| a.string_arg("name")) {
+            out.push_str(&format!("{}#[serde(rename = {:?})]\n", indent, name));
+        }
+    }
+    out
+}
+
+/// Returns the type of a field, which is wrapped in `Option` if the field is optional.
+fn field_type(field: &Field, scope: &Scope) -> String {
+    match field.optional() {
+        true => format!("Option<{}>", rust_type(field.type_(), scope)),
+        false => rust_type(field.type_(), scope)
+    }
+}
+
+/// Converts a default value into an expression of the field's type. `resolve` has already checked
+/// that it matches. A newtype's value is that of the type it wraps.
+fn rust_literal(literal: &Literal, type_ref: &TypeRef, scope: &Scope) -> String {
+    // A newtype wraps the value of the type it names, which may itself be a newtype.
+    let mut newtypes = vec![];
+    let mut type_ref = scope.root.unaliased(type_ref);
+    while let Some(ResolvedType::Defined(id)) = type_ref.resolved() {
+        match (scope.root.context().get(*id), scope.root.type_def(*id).map(TypeDef::type_)) {
+            (Some(info), Some(Type::Newtype(target))) if !newtypes.iter().any(|(found, _)| found == id) => {
+                newtypes.push((*id, scope.path_to(info)));
+                type_ref = scope.root.unaliased(target);
+            },
+            _ => break
+        }
+    }
+    newtypes.iter().rev().fold(rust_value(literal, type_ref, scope), |value, (_, path)| format!("{}({})", path, value))
+}
+
+/// Converts a default value of a type other than a newtype.
+fn rust_value(literal: &Literal, type_ref: &TypeRef, scope: &Scope) -> String {
+    let params = type_ref.params();
+    let join = |items: &[Literal], type_ref: &TypeRef| items.iter()
+        .map(|item| rust_literal(item, type_ref, scope))
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    match (type_ref.resolved(), literal) {
+        (Some(ResolvedType::Primitive(Primitive::Float32 | Primitive::Float64)), Literal::Int(i)) => format!("{:?}", *i as f64),
+        (Some(ResolvedType::Primitive(Primitive::Bytes)), Literal::List(items)) =>
+            format!("vec![{}]", items.iter().map(Literal::to_string).collect::<Vec<String>>().join(", ")),
+        (Some(ResolvedType::Container(Container::List)), Literal::List(items)) => format!("vec![{}]", join(items, &params[0])),
+        (Some(ResolvedType::Container(Container::Array)), Literal::List(items)) => format!("[{}]", join(items, &params[0])),
+        (Some(ResolvedType::Container(Container::Set)), Literal::List(items)) =>
+            format!("vec![{}].into_iter().collect()", join(items, &params[0])),
+        (Some(ResolvedType::Container(Container::Map)), Literal::Map(entries)) => {
+            let entries = entries.iter()
+                .map(|(key, value)| format!("({}, {})", rust_literal(key, &params[0], scope), rust_literal(value, &params[1], scope)))
+                .collect::<Vec<String>>();
+            format!("vec![{}].into_iter().collect()", entries.join(", "))
+        },
+        (Some(ResolvedType::Container(Container::Option)), _) => format!("Some({})", rust_literal(literal, &params[0], scope)),
+        (Some(ResolvedType::Defined(id)), Literal::Variant(name)) => match (scope.root.context().get(*id), name.last()) {
+            (Some(info), Some(variant)) => format!("{}::{}", scope.path_to(info), type_name(variant)),
+            _ => literal.to_string()
+        },
+        (_, Literal::String(s)) => format!("String::from({:?})", s),
+        _ => literal.to_string()
+    }
+}
+
+/// Whether a field without a default can be filled in with `Default::default()`.
+fn is_defaultable(type_ref: &TypeRef, root: &Root, defaulted: &HashSet<TypeId>) -> bool {
+    let type_ref = root.unaliased(type_ref);
+    match type_ref.resolved() {
+        Some(ResolvedType::Defined(id)) => defaulted.contains(id) && type_ref.params().iter().all(|p| is_defaultable(p, root, defaulted)),
+        // `Default` is only implemented for arrays of up to 32 values.
+        Some(ResolvedType::Container(Container::Array)) =>
+            type_ref.length().is_some_and(|length| length <= 32) && is_defaultable(&type_ref.params()[0], root, defaulted),
+        Some(_) => true,
+        None => false
+    }
+}
+
+fn has_default_impl(s: &Struct, root: &Root, defaulted: &HashSet<TypeId>) -> bool {
+    s.fields().iter().any(|f| f.default().is_some())
+        && s.fields().iter().all(|f| f.default().is_some() || *f.optional() || is_defaultable(f.type_(), root, defaulted))
+}
+
+/// Returns the structs that get a `Default` impl: those with at least one default value whose other
+/// fields all have types that implement `Default`.
+fn defaulted_structs(root: &Root) -> HashSet<TypeId> {
+    let structs = root.files().iter()
+        .flat_map(|file| file.types().iter().filter_map(move |type_def| match type_def.type_() {
+            Type::Struct(s) => root.context()
+                .lookup(&file.namespace().with_appended(type_def.name()))
+                .map(|id| (id, s)),
+            _ => None
+        }))
+        .collect::<Vec<(TypeId, &Struct)>>();
+
+    let mut defaulted = HashSet::new();
+    loop {
+        let next = structs.iter()
+            .find(|(id, s)| !defaulted.contains(id) && has_default_impl(s, root, &defaulted))
+            .map(|(id, _)| *id);
+        match next {
+            Some(id) => defaulted.insert(id),
+            None => return defaulted
+        };
+    }
+}
+
+/// Warns about structs with default values that get no `Default` impl, because another of their
+/// fields has neither a default value nor a type implementing `Default`.
+fn dropped_defaults(root: &Root, defaulted: &HashSet<TypeId>) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    for file in root.files().iter().filter(|f| !f.external()) {
+        for type_def in file.types() {
+            let Type::Struct(s) = type_def.type_() else {
+                continue;
+            };
+            let name = file.namespace().with_appended(type_def.name());
+            if root.context().lookup(&name).is_none_or(|id| defaulted.contains(&id)) {
+                continue;
+            }
+            let defaults = s.fields().iter()
+                .filter(|f| f.default().is_some())
+                .map(|f| format!("\"{}\"", f.name()))
+                .collect::<Vec<String>>();
+            let blocking = s.fields().iter()
+                .find(|f| f.default().is_none() && !*f.optional() && !is_defaultable(f.type_(), root, defaulted));
+            if let (false, Some(blocking)) = (defaults.is_empty(), blocking) {
+                diagnostics.push(Diagnostic {
+                    severity: Severity::Warning,
+                    message: format!(
+                        "Dropped the default values of {} in \"{}\": field \"{}\" has no default value and its type doesn't implement Default",
+                        defaults.join(", "), name, blocking.name()
+                    )
+                });
+            }
+        }
+    }
+    diagnostics
+}
+
+/// The types and generic parameters that need `Eq` and `Hash`: those used as `Map` keys or `Set`
+/// values, those held by one and the arguments of generic parameters that need them.
+#[derive(Default)]
+struct Hashed {
+    types: HashSet<TypeId>,
+
+    /// The index of a generic parameter of a type.
+    params: HashSet<(TypeId, usize)>
+}
+
+impl Hashed {
+    /// Marks what `type_ref`, used in the definition of `id`, needs hashed. `hash` is whether values
+    /// of `type_ref` are hashed and `bound` whether generic parameters then need to be bounded. The
+    /// parameters of a type that is only hashed itself are bounded by its derives.
+    fn mark(&mut self, type_ref: &TypeRef, id: TypeId, hash: bool, bound: bool) {
+        let params = type_ref.params();
+        match type_ref.resolved() {
+            Some(ResolvedType::Container(Container::Map | Container::Set)) => {
+                self.mark(&params[0], id, true, true);
+                params[1..].iter().for_each(|param| self.mark(param, id, hash, bound));
+            },
+            Some(ResolvedType::Container(_)) => params.iter().for_each(|param| self.mark(param, id, hash, bound)),
+            Some(ResolvedType::Defined(found)) => {
+                if hash {
+                    self.types.insert(*found);
+                }
+                for (index, param) in params.iter().enumerate() {
+                    let hashed = self.params.contains(&(*found, index));
+                    self.mark(param, id, hash || hashed, bound || hashed);
+                }
+            },
+            Some(ResolvedType::Param(index)) if hash && bound => {
+                self.params.insert((id, *index));
+            },
+            _ => {}
+        }
+    }
+}
+
+/// Finds the types and generic parameters that need `Eq` and `Hash`. Marking one may require others
+/// that were already visited, so this repeats until nothing changes.
+fn hashed_types(root: &Root) -> Hashed {
+    let types = root.files().iter()
+        .flat_map(|file| file.types().iter().filter_map(move |type_def| {
+            root.context().lookup(&file.namespace().with_appended(type_def.name())).map(|id| (id, type_def.type_()))
+        }))
+        .collect::<Vec<(TypeId, &Type)>>();
+
+    let mut hashed = Hashed::default();
+    loop {
+        let marked = (hashed.types.len(), hashed.params.len());
+        for (id, type_) in &types {
+            let hash = hashed.types.contains(id);
+            match type_ {
+                Type::Struct(s) => s.fields().iter().for_each(|field| hashed.mark(field.type_(), *id, hash, false)),
+                Type::Enum(e) => e.fields().iter().for_each(|field| hashed.mark(field.type_(), *id, hash, false)),
+                Type::Alias(target) | Type::Newtype(target) => hashed.mark(target, *id, hash, false),
+                Type::Primitive(_) | Type::CEnum(_) => {}
+            }
+        }
+        if marked == (hashed.types.len(), hashed.params.len()) {
+            return hashed;
+        }
+    }
+}
+
+/// Returns the generic parameter list for a type, e.g. `<T, U>`.
+fn generics(params: &[Name]) -> String {
+    if params.is_empty() {
+        return String::new();
+    }
+    format!("<{}>", params.iter().map(type_name).collect::<Vec<String>>().join(", "))
+}
+
+/// Returns the generic parameter list for a type declaration. Parameters used as `Map` keys or `Set`
+/// values are bounded by `Eq` and `Hash`, e.g. `<K: Eq + std::hash::Hash, V>`.
+fn declared_generics(scope: &Scope) -> String {
+    if scope.params.is_empty() {
+        return String::new();
+    }
+    let params = scope.params.iter().zip(&scope.hashed_params)
+        .map(|(param, hashed)| match hashed {
+            true => format!("{}: Eq + std::hash::Hash", type_name(param)),
+            false => type_name(param)
+        })
+        .collect::<Vec<String>>();
+    format!("<{}>", params.join(", "))
+}
+
+fn generate_struct(out: &mut String, name: &Name, s: &Struct, scope: &Scope) {
+    out.push_str(&format!("pub struct {}{} {{\n", type_name(name), declared_generics(scope)));
+    for field in s.fields() {
+        out.push_str(&doc_comment(field.doc(), "    "));
+        // Absent optional fields are left out when serializing.
+        if *field.optional() && scope.config.uses_serde() {
+            out.push_str("    #[serde(default, skip_serializing_if = \"Option::is_none\")]\n");
+        }
+        out.push_str(&attributes(field.annotations(), scope.config, "    "));
+        out.push_str(&format!("    pub {}: {},\n", field_name(field.name()), field_type(field, scope)));
+    }
+    out.push_str("}\n");
+
+    if has_default_impl(s, scope.root, scope.defaulted) {
+        generate_default(out, name, s, scope);
+    }
+}
+
+/// Generic parameters are required to implement `Default` as fields of their type may need it, on
+/// top of the bounds they are declared with. The struct, its fields or the variants used as defaults
+/// may be `@deprecated`, which shouldn't warn here.
+fn generate_default(out: &mut String, name: &Name, s: &Struct, scope: &Scope) {
+    let bounds = scope.params.iter().zip(&scope.hashed_params)
+        .map(|(p, hashed)| match hashed {
+            true => format!("{}: Default + Eq + std::hash::Hash", type_name(p)),
+            false => format!("{}: Default", type_name(p))
+        })
+        .collect::<Vec<String>>();
+    let bounds = match bounds.is_empty() {
+        true => String::new(),
+        false => format!("<{}>", bounds.join(", "))
+    };
+
+    out.push_str("\n#[allow(deprecated)]");
+    out.push_str(&format!("\nimpl{} Default for {}{} {{\n", bounds, type_name(name), generics(&scope.params)));
+    out.push_str("    fn default() -> Self {\n");
+    out.push_str(&format!("        {} {{\n", type_name(name)));
+    for field in s.fields() {
+        let value = match (field.default(), field.optional()) {
+            (Some(default), true) => format!("Some({})", rust_literal(default, field.type_(), scope)),
+            (Some(default), false) => rust_literal(default, field.type_(), scope),
+            (None, _) => "Default::default()".to_string()
+        };
+        out.push_str(&format!("            {}: {},\n", field_name(field.name()), value));
+    }
+    out.push_str("        }\n    }\n}\n");
+}
+
+fn generate_enum(out: &mut String, name: &Name, e: &Enum, scope: &Scope) {
+    out.push_str(&format!("pub enum {}{} {{\n", type_name(name), declared_generics(scope)));
+    for field in e.fields() {
+        out.push_str(&doc_comment(field.doc(), "    "));
+        out.push_str(&attributes(field.annotations(), scope.config, "    "));
+        match field.type_().primitive() {
+            Some(Primitive::Unit) => out.push_str(&format!("    {},\n", type_name(field.name()))),
+            _ => out.push_str(&format!("    {}({}),\n", type_name(field.name()), rust_type(field.type_(), scope)))
+        }
+    }
+    out.push_str("}\n");
+}
+
+/// The enum is `#[repr]` its backing type so that its values can be cast to their discriminants.
+fn generate_c_enum(out: &mut String, name: &Name, e: &CEnum, scope: &Scope) {
+    let backing = e.backing().as_ref().and_then(TypeRef::primitive).unwrap_or(Primitive::Int32);
+    out.push_str(&format!("#[repr({})]\n", primitive_type(&backing)));
+    out.push_str(&format!("pub enum {} {{\n", type_name(name)));
+    for value in e.values() {
+        out.push_str(&doc_comment(value.doc(), "    "));
+        out.push_str(&attributes(value.annotations(), scope.config, "    "));
+        match value.discriminant() {
+            Some(discriminant) => out.push_str(&format!("    {} = {},\n", type_name(value.name()), discriminant)),
+            None => out.push_str(&format!("    {},\n", type_name(value.name())))
+        }
+    }
+    out.push_str("}\n");
+}
+
+fn generate_alias(out: &mut String, type_def: &TypeDef, target: &TypeRef, scope: &Scope) {
+    out.push_str(&doc_comment(type_def.doc(), ""));
+    out.push_str(&deprecated_attribute(type_def.annotations(), ""));
+    out.push_str(&format!("pub type {} = {};\n", type_name(type_def.name()), rust_type(target, scope)));
+}
+
+fn generate_newtype(out: &mut String, name: &Name, target: &TypeRef, scope: &Scope) {
+    out.push_str(&format!("pub struct {}(pub {});\n", type_name(name), rust_type(target, scope)));
+}
+
+/// String constants are `&str` as a `String` can't be built in a constant.
+fn generate_const(out: &mut String, const_def: &ConstDef, scope: &Scope) {
+    let (type_, value) = match (scope.root.unaliased(const_def.type_()).primitive(), const_def.value()) {
+        (Some(Primitive::String), Literal::String(s)) => ("&str".to_string(), format!("{:?}", s)),
+        (_, value) => (rust_type(const_def.type_(), scope), rust_literal(value, const_def.type_(), scope))
+    };
+    out.push_str(&doc_comment(const_def.doc(), ""));
+    out.push_str(&deprecated_attribute(const_def.annotations(), ""));
+    out.push_str(&format!("pub const {}: {} = {};\n", const_name(const_def.name()), type_, value));
+}
+
+fn generate_type_def(out: &mut String, type_def: &TypeDef, id: Option<TypeId>, scope: &Scope) {
+    let scope = scope.with_params(id, type_def.params());
+    if let Type::Struct(_) | Type::Enum(_) | Type::CEnum(_) | Type::Newtype(_) = type_def.type_() {
+        let hashed = id.is_some_and(|id| scope.hashed.types.contains(&id));
+        out.push_str(&doc_comment(type_def.doc(), ""));
+        out.push_str(&scope.config.derive_attribute(type_def, hashed));
+        out.push_str(&attributes(type_def.annotations(), scope.config, ""));
+    }
+    match type_def.type_() {
+        Type::Struct(s) => generate_struct(out, type_def.name(), s, &scope),
+        Type::Enum(e) => generate_enum(out, type_def.name(), e, &scope),
+        Type::CEnum(e) => generate_c_enum(out, type_def.name(), e, &scope),
+        Type::Alias(target) => generate_alias(out, type_def, target, &scope),
+        Type::Newtype(target) => generate_newtype(out, type_def.name(), target, &scope),
+        Type::Primitive(_) => {}
+    }
+}
+
+fn generate_module(files: &mut Vec<OutputFile>, module: &Module, scope: &Scope) {
+    let mut out = String::from("// Generated by dcg-rust. Do not edit.\n");
+
+    for child in module.children.keys() {
+        out.push_str(&format!("pub mod {};\n", child));
+    }
+
+    if !module.consts.is_empty() {
+        out.push('\n');
+    }
+    for const_def in &module.consts {
+        generate_const(&mut out, const_def, scope);
+    }
+
+    for type_def in &module.types {
+        let id = module.namespace.and_then(|namespace| scope.root.context().lookup(&namespace.with_appended(type_def.name())));
+        out.push('\n');
+        generate_type_def(&mut out, type_def, id, scope);
+    }
+
+    for (name, child) in module.children.iter().filter(|(_, child)| child.generated) {
+        generate_module(files, child, &scope.child(name));
+    }
+
+    files.push(OutputFile { path: scope.path.iter().collect::<PathBuf>().join("mod.rs"), contents: out });
+}
+
+/// Generates one `mod.rs` per namespace component, with warnings about default values that had to
+/// be dropped.
+pub fn generate(root: &Root, config: &Config) -> Response {
+    let mut tree = Module::default();
+    for file in root.files().iter().filter(|f| !f.external() || config.external.is_none()) {
+        tree.insert(file.namespace().names(), file);
+    }
+
+    let defaulted = defaulted_structs(root);
+    let hashed = hashed_types(root);
+    let mut files = vec![];
+    let scope = Scope { root, config, defaulted: &defaulted, hashed: &hashed, path: vec![], params: vec![], hashed_params: vec![] };
+    generate_module(&mut files, &tree, &scope);
+    Response { diagnostics: dropped_defaults(root, &defaulted), ..Response::from_files(files) }
+}
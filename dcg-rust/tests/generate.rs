@@ -0,0 +1,379 @@
+use std::{collections::BTreeMap, path::PathBuf, process::Command};
+
+use app_common::{CodeGenerator, options::Options, protocol::{Response, Severity}};
+use dcg_rust::RustGenerator;
+
+/// Generates Rust from `(path, source)` pairs, of which files named in `external` are imports.
+fn respond(files: &[(&str, &str)], external: &[&str], options: &[(&str, &str)]) -> Response {
+    let files = files.iter()
+        .map(|(path, source)| (PathBuf::from(path), source.to_string()))
+        .collect::<Vec<(PathBuf, String)>>();
+    let mut root = parser::parse(&files).expect("parse failed");
+    for file in root.files_mut() {
+        let path = file.path().clone().unwrap_or_default();
+        file.set_external(external.iter().any(|e| path == PathBuf::from(e)));
+    }
+    let root = ast::resolve(root).expect("resolve failed");
+    let options = Options::new(options.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect());
+    RustGenerator.generate(&root, &options).expect("generate failed")
+}
+
+/// Returns the contents of every generated file by path.
+fn generate(files: &[(&str, &str)], external: &[&str], options: &[(&str, &str)]) -> BTreeMap<PathBuf, String> {
+    respond(files, external, options).files.into_iter().map(|file| (file.path, file.contents)).collect()
+}
+
+/// Compiles the generated module tree as a library with rustc, failing with its errors.
+fn compile(name: &str, files: &BTreeMap<PathBuf, String>) {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(env!("CARGO_CRATE_NAME")).join(name);
+    let _ = std::fs::remove_dir_all(&dir);
+    for (path, contents) in files {
+        let path = dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+    let output = Command::new(std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string()))
+        .args(["--crate-type", "lib", "--crate-name", "generated", "--edition", "2021", "--emit", "metadata", "--out-dir"])
+        .arg(&dir)
+        .arg(dir.join("mod.rs"))
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+const OTHER: &str = "namespace other\nstruct Thing { x: Int32 }\n";
+const MAIN: &str = "namespace app\nimport \"other.dcg\"\nstruct User { thing: other.Thing }\n";
+
+#[test]
+fn declares_modules_of_external_files() {
+    let files = generate(&[("main.dcg", MAIN), ("other.dcg", OTHER)], &["other.dcg"], &[]);
+    assert_eq!(files.keys().collect::<Vec<_>>(), vec![&PathBuf::from("app/mod.rs"), &PathBuf::from("mod.rs")]);
+    assert_eq!(files[&PathBuf::from("mod.rs")], "// Generated by dcg-rust. Do not edit.\npub mod app;\npub mod other;\n");
+    assert!(files[&PathBuf::from("app/mod.rs")].contains("pub thing: super::other::Thing,"));
+}
+
+#[test]
+fn refers_to_external_files_through_configured_path() {
+    let files = generate(&[("main.dcg", MAIN), ("other.dcg", OTHER)], &["other.dcg"], &[("external", "crate::schema")]);
+    assert_eq!(files[&PathBuf::from("mod.rs")], "// Generated by dcg-rust. Do not edit.\npub mod app;\n");
+    assert!(files[&PathBuf::from("app/mod.rs")].contains("pub thing: crate::schema::other::Thing,"));
+}
+
+#[test]
+fn warns_about_dropped_defaults() {
+    let source = "namespace app\nenum Kind { A: Unit }\nstruct S { kind: Kind\n count: Int32 = 1 }\n";
+    let response = respond(&[("a.dcg", source)], &[], &[]);
+    assert!(!response.files[0].contents.contains("impl Default"));
+    assert_eq!(response.diagnostics.len(), 1);
+    assert_eq!(response.diagnostics[0].severity, Severity::Warning);
+    assert_eq!(
+        response.diagnostics[0].message,
+        "Dropped the default values of \"count\" in \"app.S\": field \"kind\" has no default value and its type doesn't implement Default"
+    );
+}
+
+#[test]
+fn generates_module_tree() {
+    let source = "namespace app.model\nstruct Point { x: Float64\n y: Float64 }\nenum Shape { Circle: Float64\n Empty: Unit\n Poly: List<Point> }\n";
+    let files = generate(&[("a.dcg", source)], &[], &[]);
+    assert_eq!(files[&PathBuf::from("mod.rs")], "// Generated by dcg-rust. Do not edit.\npub mod app;\n");
+    assert_eq!(files[&PathBuf::from("app/mod.rs")], "// Generated by dcg-rust. Do not edit.\npub mod model;\n");
+    assert_eq!(files[&PathBuf::from("app/model/mod.rs")], "// Generated by dcg-rust. Do not edit.
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Shape {
+    Circle(f64),
+    Empty,
+    Poly(Vec<Point>),
+}
+");
+}
+
+#[test]
+fn refers_to_other_namespaces_by_relative_path() {
+    let files = generate(&[
+        ("a.dcg", "namespace a.inner\nstruct X { y: b.Y }\n"),
+        ("b.dcg", "namespace b\nstruct Y { x: Int32 }\n")
+    ], &[], &[]);
+    assert!(files[&PathBuf::from("a/inner/mod.rs")].contains("pub y: super::super::b::Y,"));
+}
+
+#[test]
+fn generates_generic_types() {
+    let source = "namespace app\nstruct Page<T> { items: List<T>\n next: String }\nenum Either<L R> { Left: L\n Right: R }\nstruct Users { e: Either<String Page<Int32>> }\n";
+    let files = generate(&[("a.dcg", source)], &[], &[]);
+    assert_eq!(files[&PathBuf::from("app/mod.rs")], "// Generated by dcg-rust. Do not edit.
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Either<L, R> {
+    Left(L),
+    Right(R),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Users {
+    pub e: Either<String, Page<i32>>,
+}
+");
+}
+
+#[test]
+fn generates_containers() {
+    let source = "namespace app\nstruct C { m: Map<String Int32>\n s: Set<String>\n o: Option<Int64>\n l: List<Bool> }\n";
+    let files = generate(&[("a.dcg", source)], &[], &[]);
+    assert_eq!(files[&PathBuf::from("app/mod.rs")], "// Generated by dcg-rust. Do not edit.
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct C {
+    pub m: std::collections::HashMap<String, i32>,
+    pub s: std::collections::HashSet<String>,
+    pub o: Option<i64>,
+    pub l: Vec<bool>,
+}
+");
+}
+
+const KEYS: &str = "namespace app
+enum Color : UInt8 { Red\n Green }
+struct Key { id: Int32\n name: String }
+newtype Id = Int64
+type Ids = Set<Id>
+enum Shape { circle: Int32\n square: Key }
+struct Wrap<T> { value: Option<T> }
+struct Page<T> { tags: Set<T>\n size: Int32 = 10 }
+struct Book<U> { page: Page<U> }
+struct Index {
+    by_key: Map<Key Int32>
+    colors: Set<Color>
+    ids: Ids
+    shapes: Set<Shape>
+    wrapped: Map<Wrap<Key> String>
+    pages: List<Page<Color>>
+    book: Book<Id>
+    floats: Wrap<Float64>
+}
+";
+
+#[test]
+fn derives_hash_for_keys() {
+    let files = generate(&[("a.dcg", KEYS)], &[], &[]);
+    let module = &files[&PathBuf::from("app/mod.rs")];
+    for declaration in ["#[repr(u8)]\npub enum Color {", "pub struct Key {", "pub struct Id(", "pub enum Shape {", "pub struct Wrap<T> {"] {
+        assert!(module.contains(&format!("#[derive(Clone, Debug, PartialEq, Eq, Hash)]\n{}", declaration)), "{}", declaration);
+    }
+    assert!(module.contains("#[derive(Clone, Debug, PartialEq)]\npub struct Index {"));
+    assert!(module.contains("pub struct Page<T: Eq + std::hash::Hash> {"));
+    assert!(module.contains("pub struct Book<U: Eq + std::hash::Hash> {"));
+    assert!(module.contains("impl<T: Default + Eq + std::hash::Hash> Default for Page<T> {"));
+    compile("keys", &files);
+    compile("keys_without_derives", &generate(&[("a.dcg", KEYS)], &[], &[("derive", "Debug")]));
+}
+
+#[test]
+fn derives_configured_traits() {
+    let files = generate(&[("a.dcg", "namespace app\nstruct S { x: Int32 }\n")], &[], &[("derive", "Debug, serde::Serialize,")]);
+    assert!(files[&PathBuf::from("app/mod.rs")].contains("#[derive(Debug, serde::Serialize)]\npub struct S {"));
+}
+
+const DEFAULTS: &str = "namespace app\nenum Color : UInt8 { Red = 1\n Green = 2 }\nstruct S { n: Int32 = 5\n name: String = \"x\"\n tags: List<String> = [\"a\" \"b\"]\n m: Map<String Int32> = {\"k\": 1}\n color: Color = Color.Green }\n";
+
+#[test]
+fn generates_default_impls() {
+    let files = generate(&[("a.dcg", DEFAULTS)], &[], &[]);
+    let module = &files[&PathBuf::from("app/mod.rs")];
+    assert_eq!(&module[module.find("#[allow(deprecated)]").unwrap()..], "#[allow(deprecated)]
+impl Default for S {
+    fn default() -> Self {
+        S {
+            n: 5,
+            name: String::from(\"x\"),
+            tags: vec![String::from(\"a\"), String::from(\"b\")],
+            m: vec![(String::from(\"k\"), 1)].into_iter().collect(),
+            color: Color::Green,
+        }
+    }
+}
+");
+}
+
+#[test]
+fn escapes_strings() {
+    let files = generate(&[("a.dcg", "namespace app\nstruct S { name: String = \"a\\\"b\\\\c\\n\" }\n")], &[], &[]);
+    assert!(files[&PathBuf::from("app/mod.rs")].contains("            name: String::from(\"a\\\"b\\\\c\\n\"),\n"));
+}
+
+#[test]
+fn generates_optional_fields() {
+    let files = generate(&[("a.dcg", "namespace app\nstruct S { nick?: String\n age?: Int32 = 3 }\n")], &[], &[]);
+    assert_eq!(files[&PathBuf::from("app/mod.rs")], "// Generated by dcg-rust. Do not edit.
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct S {
+    pub nick: Option<String>,
+    pub age: Option<i32>,
+}
+
+#[allow(deprecated)]
+impl Default for S {
+    fn default() -> Self {
+        S {
+            nick: Default::default(),
+            age: Some(3),
+        }
+    }
+}
+");
+}
+
+const ANNOTATED: &str = "namespace app\n@rust(derive=\"Hash, Eq\")\n@deprecated(note=\"use V2\")\nstruct S {\n @json(name=\"user_id\")\n @deprecated\n id: Int32\n}\n";
+
+#[test]
+fn generates_attributes_from_annotations() {
+    let files = generate(&[("a.dcg", ANNOTATED)], &[], &[]);
+    assert_eq!(files[&PathBuf::from("app/mod.rs")], "// Generated by dcg-rust. Do not edit.
+
+#[derive(Clone, Debug, PartialEq, Hash, Eq)]
+#[deprecated(note = \"use V2\")]
+pub struct S {
+    #[deprecated]
+    pub id: i32,
+}
+");
+}
+
+#[test]
+fn renames_fields_for_serde() {
+    let files = generate(&[("a.dcg", ANNOTATED)], &[], &[("derive", "Debug,serde::Serialize")]);
+    assert!(files[&PathBuf::from("app/mod.rs")].contains("    #[deprecated]\n    #[serde(rename = \"user_id\")]\n    pub id: i32,\n"));
+}
+
+const DOCUMENTED: &str = "namespace app\n/// A user.\n/// Second line.\nstruct User {\n /// The id.\n id: Int32\n}\n/// Kinds.\nenum Kind {\n /// First.\n A: Unit\n}\n/// Colors.\nenum Color : UInt8 {\n /// Red.\n Red = 1\n}\n";
+
+#[test]
+fn generates_doc_comments() {
+    let files = generate(&[("a.dcg", DOCUMENTED)], &[], &[]);
+    assert_eq!(files[&PathBuf::from("app/mod.rs")], "// Generated by dcg-rust. Do not edit.
+
+/// A user.
+/// Second line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct User {
+    /// The id.
+    pub id: i32,
+}
+
+/// Kinds.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Kind {
+    /// First.
+    A,
+}
+
+/// Colors.
+#[derive(Clone, Debug, PartialEq)]
+#[repr(u8)]
+pub enum Color {
+    /// Red.
+    Red = 1,
+}
+");
+}
+
+const CONSTANTS: &str = "namespace app\n/// Max.\nconst MAX: UInt8 = 10\nconst NAME: String = \"n\"\nconst RATIO: Float64 = 0.5\ntype Port = UInt16\nconst PORT: Port = 80\n";
+
+#[test]
+fn generates_constants() {
+    let files = generate(&[("a.dcg", CONSTANTS)], &[], &[]);
+    assert_eq!(files[&PathBuf::from("app/mod.rs")], "// Generated by dcg-rust. Do not edit.
+
+/// Max.
+pub const MAX: u8 = 10;
+pub const NAME: &str = \"n\";
+pub const RATIO: f64 = 0.5;
+pub const PORT: Port = 80;
+
+pub type Port = u16;
+");
+}
+
+#[test]
+fn generates_c_enums() {
+    let files = generate(&[("a.dcg", "namespace app\nenum Flags : UInt16 { A = 1\n B\n C = 10\n D }\nenum Plain { X, Y }\n")], &[], &[]);
+    assert_eq!(files[&PathBuf::from("app/mod.rs")], "// Generated by dcg-rust. Do not edit.
+
+#[derive(Clone, Debug, PartialEq)]
+#[repr(u16)]
+pub enum Flags {
+    A = 1,
+    B = 2,
+    C = 10,
+    D = 11,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[repr(i32)]
+pub enum Plain {
+    X = 0,
+    Y = 1,
+}
+");
+}
+
+#[test]
+fn generates_aliases_and_newtypes() {
+    let source = "namespace app\ntype Tags = List<String>\nnewtype UserId = Int64\nnewtype Tree = List<Tree>\nstruct User { id: UserId = 7\n tags: Tags = [\"a\"] }\n";
+    let files = generate(&[("a.dcg", source)], &[], &[]);
+    assert_eq!(files[&PathBuf::from("app/mod.rs")], "// Generated by dcg-rust. Do not edit.
+
+pub type Tags = Vec<String>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct UserId(pub i64);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tree(pub Vec<Tree>);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct User {
+    pub id: UserId,
+    pub tags: Tags,
+}
+
+#[allow(deprecated)]
+impl Default for User {
+    fn default() -> Self {
+        User {
+            id: UserId(7),
+            tags: vec![String::from(\"a\")],
+        }
+    }
+}
+");
+}
+
+#[test]
+fn generates_arrays_and_bytes() {
+    let source = "namespace app\nstruct S { hash: Array<UInt8 32>\n data: Bytes\n grid: Array<Array<Int32 3> 2> }\n";
+    let files = generate(&[("a.dcg", source)], &[], &[]);
+    assert_eq!(files[&PathBuf::from("app/mod.rs")], "// Generated by dcg-rust. Do not edit.
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct S {
+    pub hash: [u8; 32],
+    pub data: Vec<u8>,
+    pub grid: [[i32; 3]; 2],
+}
+");
+}
@@ -0,0 +1,92 @@
+use std::fmt::Display;
+
+use ast::ResolveError;
+
+use crate::{loader::LoadError, report::{Report, Status}};
+
+/// Everything that can make dcg fail. Each kind of failure exits with its own code.
+#[derive(Debug)]
+pub enum DcgError {
+    /// The command line is invalid.
+    Argument(String),
+
+    /// Input files or their imports couldn't be loaded.
+    Io(Vec<LoadError>),
+
+    /// Input files have syntax errors.
+    Parse(Vec<LoadError>),
+
+    /// The input parsed but refers to unknown types, redefines types or uses generics incorrectly.
+    Semantic(Vec<ResolveError>),
+
+    /// No executable was found for a generator.
+    PluginNotFound(String),
+
+    /// Some generators failed or timed out. Has the reports of every generator.
+    PluginFailed(Vec<Report>)
+}
+
+impl DcgError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            DcgError::PluginFailed(_) => 1,
+            DcgError::Argument(_) => 2,
+            DcgError::Io(_) => 3,
+            DcgError::Parse(_) => 4,
+            DcgError::Semantic(_) => 5,
+            DcgError::PluginNotFound(_) => 6
+        }
+    }
+}
+
+impl From<&str> for DcgError {
+    fn from(msg: &str) -> Self {
+        DcgError::Argument(String::from(msg))
+    }
+}
+
+impl From<Vec<LoadError>> for DcgError {
+    /// Syntax errors are only reported as such if all of the input could be read.
+    fn from(errors: Vec<LoadError>) -> Self {
+        if errors.iter().all(|e| matches!(e, LoadError::Parse(_))) {
+            DcgError::Parse(errors)
+        } else {
+            DcgError::Io(errors)
+        }
+    }
+}
+
+impl From<Vec<ResolveError>> for DcgError {
+    fn from(errors: Vec<ResolveError>) -> Self {
+        DcgError::Semantic(errors)
+    }
+}
+
+fn write_lines<T: Display>(f: &mut std::fmt::Formatter<'_>, items: &[T]) -> std::fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            f.write_str("\n")?;
+        }
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
+
+/// Multiple errors are written one per line.
+impl Display for DcgError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DcgError::Argument(msg) => f.write_str(msg),
+            DcgError::Io(errors) | DcgError::Parse(errors) => write_lines(f, errors),
+            DcgError::Semantic(errors) => write_lines(f, errors),
+            DcgError::PluginNotFound(gen) => write!(f, "Unable to find generator: {}", gen),
+            DcgError::PluginFailed(reports) => {
+                let failed = reports.iter()
+                    .filter(|r| r.status != Status::Ok)
+                    .map(|r| r.generator.as_str())
+                    .collect::<Vec<&str>>();
+                write!(f, "Generators failed: {}", failed.join(", "))
+            }
+        }
+    }
+}
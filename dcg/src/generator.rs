@@ -1,72 +1,340 @@
-use std::{io::{Read, Write}, net::TcpListener, path::PathBuf, process::{Command, Stdio}, time::Duration};
+use std::{collections::BTreeMap, io::{ErrorKind, Read, Write}, net::{Shutdown, TcpListener}, path::{Path, PathBuf}, process::{Child, Command, ExitStatus, Stdio}, sync::{Arc, Mutex}, thread::JoinHandle, time::{Duration, Instant}};
 
+use app_common::{AppError, options::Options, protocol::{Capabilities, Diagnostic, Feature, PROTOCOL_VERSION, Request, Response, Severity}};
 use ast::Root;
 
-pub struct Generator {
-    cmd: String
+pub use app_common::CodeGenerator;
+
+use crate::error::DcgError;
+
+/// How the AST is handed to a generator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Protocol {
+    /// Write the AST to the generator's stdin and read a `Response` from its stdout.
+    Stdio,
+
+    /// Pass the generator a port to connect to, write the AST to the connection and read a
+    /// `Response` back from it.
+    Tcp
+}
+
+impl Protocol {
+    pub fn from(protocol: &str) -> Option<Protocol> {
+        match protocol {
+            "stdio" => Some(Protocol::Stdio),
+            "tcp" => Some(Protocol::Tcp),
+            _ => None
+        }
+    }
+}
+
+/// Returns the generator built into dcg with this name.
+pub fn builtin(name: &str) -> Option<Box<dyn CodeGenerator>> {
+    match name {
+        "rust" => Some(Box::new(dcg_rust::RustGenerator)),
+        "cpp" => Some(Box::new(dcg_cpp::CppGenerator)),
+        _ => None
+    }
+}
+
+/// Finds a generator by name: a path to an executable, the name of a built-in generator or the suffix
+/// of a `dcg-<suffix>` executable, in that order.
+pub fn find(name: &str, protocol: Protocol, timeout: Option<Duration>, output: &Path) -> Result<Box<dyn CodeGenerator>, DcgError> {
+    let external = |cmd: String| -> Box<dyn CodeGenerator> {
+        Box::new(ExternalGenerator { cmd, protocol, timeout, output: output.to_path_buf() })
+    };
+
+    if name.contains('/') || name.contains('\\') {
+        return Ok(external(name.to_string()));
+    }
+
+    if let Some(gen) = builtin(name) {
+        return Ok(gen);
+    }
+
+    let possible_plugin_name = format!("dcg-{}", name);
+    if which::which(&possible_plugin_name).is_ok() {
+        return Ok(external(possible_plugin_name));
+    }
+
+    Err(DcgError::PluginNotFound(name.to_string()))
+}
+
+/// A generator executable, run once per `generate` call.
+pub struct ExternalGenerator {
+    cmd: String,
+    protocol: Protocol,
+
+    /// The executable is killed if it runs for longer than this.
+    timeout: Option<Duration>,
+
+    /// Passed to the executable. Its files are still written by dcg.
+    output: PathBuf
+}
+
+impl CodeGenerator for ExternalGenerator {
+    /// The executable's capabilities are checked when it is run instead.
+    fn features(&self) -> &[Feature] {
+        Feature::ALL
+    }
+
+    /// Everything the executable prints is added to the response as `Info` diagnostics.
+    fn generate(&self, ast: &Root, options: &Options) -> Result<Response, AppError> {
+        let mut log = vec![];
+        let mut response = self.run(ast, options.values(), &mut log)
+            .unwrap_or_else(|e| Response { timed_out: e.kind() == ErrorKind::TimedOut, ..Response::from_error(e.to_string()) });
+
+        let mut diagnostics = log.into_iter()
+            .map(|message| Diagnostic { severity: Severity::Info, message })
+            .collect::<Vec<Diagnostic>>();
+        diagnostics.append(&mut response.diagnostics);
+        response.diagnostics = diagnostics;
+        Ok(response)
+    }
 }
 
-impl Generator {
-    pub fn from(gen: &str) -> Generator {
-        if gen.contains("/") || gen.contains("\\") {
-            return Generator { cmd: gen.to_string() }
+impl ExternalGenerator {
+    /// Asks the generator which protocol version and language features it supports.
+    fn capabilities(&self, deadline: Option<Instant>) -> std::io::Result<Capabilities> {
+        let mut process = Command::new(&self.cmd)
+            .arg("--capabilities")
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdout = capture(process.stdout.take().expect("Generator stdout is piped."));
+
+        let status = wait(&mut process, deadline);
+        let output = stdout.finish(deadline);
+        if !status?.success() {
+            return Err(std::io::Error::other(format!(
+                "{} does not report its capabilities and probably predates protocol version {}.",
+                self.cmd, PROTOCOL_VERSION
+            )));
         }
 
-        let possible_plugin_name = format!("dcg-{}", gen);
-        match which::which(&possible_plugin_name) {
-            Ok(_) => return Generator { cmd: possible_plugin_name},
-            Err(_) => {},
+        serde_json::from_str::<Capabilities>(&output).map_err(|e| std::io::Error::other(
+            format!("{} reported invalid capabilities: {}", self.cmd, e)
+        ))
+    }
+
+    /// Returns an error if the generator can't handle `ast`.
+    fn check_capabilities(&self, ast: &Root, deadline: Option<Instant>) -> std::io::Result<()> {
+        let capabilities = self.capabilities(deadline)?;
+        if capabilities.protocol_version != PROTOCOL_VERSION {
+            return Err(std::io::Error::other(format!(
+                "{} uses protocol version {} but dcg uses version {}.",
+                self.cmd, capabilities.protocol_version, PROTOCOL_VERSION
+            )));
         }
 
-        panic!("Unable to find generator: {}", gen)
+        let unsupported = Feature::used_by(ast).difference(&capabilities.features).copied().collect::<Vec<Feature>>();
+        if !unsupported.is_empty() {
+            return Err(std::io::Error::other(format!(
+                "{} does not support features used by the input: {:?}", self.cmd, unsupported
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Runs the generator and returns its response. The generator's files are not written.
+    ///
+    /// Everything the generator prints is captured into `log`. If it runs past the timeout it is killed
+    /// and an error of kind `TimedOut` is returned.
+    fn run(&self, ast: &Root, options: &BTreeMap<String, String>, log: &mut Vec<String>) -> std::io::Result<Response> {
+        log.push(format!("Running Generator: {}", self.cmd));
+
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        self.check_capabilities(ast, deadline)?;
+
+        let mut request = Request::new(ast.clone());
+        request.dcg_version = env!("CARGO_PKG_VERSION").to_string();
+        request.options = options.clone();
+        let request = serde_json::to_string(&request)?;
+
+        match self.protocol {
+            Protocol::Stdio => self.run_stdio(request, deadline, log),
+            Protocol::Tcp => self.run_tcp(request, deadline, log)
+        }
     }
 
-    pub fn run(&self, ast: &Root, output: &PathBuf) -> std::io::Result<()> {
-        println!("Running Generator: {}", self.cmd);
+    fn run_stdio(&self, request: String, deadline: Option<Instant>, log: &mut Vec<String>) -> std::io::Result<Response> {
+        let mut cmd = Command::new(&self.cmd);
+        cmd
+            .arg("-")
+            .arg(&self.output)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+
+        log.push(format!("Generator Command Line: {:?}", cmd));
+
+        let mut process = cmd.spawn()?;
+
+        // Write from another thread so that a generator producing output before it has read all of
+        // its input can't deadlock against us.
+        let mut stdin = process.stdin.take().expect("Generator stdin is piped.");
+        let writer = std::thread::spawn(move || stdin.write_all(request.as_bytes()));
+        let stdout = capture(process.stdout.take().expect("Generator stdout is piped."));
+        let stderr = capture(process.stderr.take().expect("Generator stderr is piped."));
+
+        let status = wait(&mut process, deadline);
+
+        let output = stdout.finish(deadline);
+        log.extend(stderr.finish(deadline).lines().map(String::from));
+        let status = status?;
+        // Only joined once the generator has exited: a killed generator may never read all of its input.
+        let written = writer.join().expect("Generator input thread panicked.");
+
+        let response = serde_json::from_str::<Response>(&output).map_err(|e| std::io::Error::other(
+            format!("Generator exited with status {} and an invalid response: {}", status, e)
+        ))?;
 
+        written?;
+        Ok(response)
+    }
+
+    fn run_tcp(&self, request: String, deadline: Option<Instant>, log: &mut Vec<String>) -> std::io::Result<Response> {
         let socket = TcpListener::bind("127.0.0.1:0")?;
         let addr = socket.local_addr()?;
 
-        println!("Generator using port: {}", addr);
+        log.push(format!("Generator using port: {}", addr));
 
         socket.set_nonblocking(true)?;
 
         let mut cmd = Command::new(&self.cmd);
         cmd
             .arg(format!("{}", addr.port()))
-            .arg(format!("{}", output.as_os_str().to_str().expect("Unknown error handling output path.")));
-        
-        println!("Generator Command Line: {:?}", cmd);
+            .arg(&self.output)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+
+        log.push(format!("Generator Command Line: {:?}", cmd));
 
         let mut process = cmd.spawn()?;
+        let stdout = capture(process.stdout.take().expect("Generator stdout is piped."));
+        let stderr = capture(process.stderr.take().expect("Generator stderr is piped."));
 
-        let mut poll = || -> std::io::Result<()> {
-            let cmd_result = process.try_wait()?;
-            if let Some(e) = cmd_result {
-                return std::io::Result::Err(std::io::Error::new(
-                    std::io::ErrorKind::Other, 
-                    format!("Generator exited before connecting with status: {}", e)
-                ));
-            }
-
-            let (mut stream, _) = socket.accept()?;
-            stream.write_all(serde_json::to_string(&ast)?.as_bytes())?;
-            drop(stream);
-            Ok(())
+        let response = exchange(&socket, &request, &mut process, deadline);
+        let status = match response {
+            Ok(_) => wait(&mut process, deadline),
+            Err(_) => kill(&mut process)
         };
 
-        loop {
-            match poll() {
-                Ok(_) => break,
-                Err(_) => {
-                    println!("Waiting for generator connection...");
-                    std::thread::sleep(Duration::from_millis(500))
-                },
-            }
+        log.extend(stdout.finish(deadline).lines().map(String::from));
+        log.extend(stderr.finish(deadline).lines().map(String::from));
+        let response = response?;
+        let status = status?;
+
+        serde_json::from_str::<Response>(&response).map_err(|e| std::io::Error::other(
+            format!("Generator exited with status {} and an invalid response: {}", status, e)
+        ))
+    }
+}
+
+fn timed_out() -> std::io::Error {
+    std::io::Error::new(ErrorKind::TimedOut, "Generator timed out.")
+}
+
+fn kill(process: &mut Child) -> std::io::Result<ExitStatus> {
+    process.kill()?;
+    process.wait()
+}
+
+/// Waits for the generator to exit, killing it if it is still running at `deadline`. Without a
+/// deadline this blocks until it exits.
+fn wait(process: &mut Child, deadline: Option<Instant>) -> std::io::Result<ExitStatus> {
+    let Some(deadline) = deadline else {
+        return process.wait();
+    };
+    loop {
+        if let Some(status) = process.try_wait()? {
+            return Ok(status);
+        }
+        if Instant::now() >= deadline {
+            kill(process)?;
+            return Err(timed_out());
         }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
 
-        process.wait()?;
+/// Output of a generator being read on another thread.
+struct Capture {
+    buf: Arc<Mutex<Vec<u8>>>,
+    reader: JoinHandle<()>
+}
 
-        Ok(())
+impl Capture {
+    /// Waits for the output to be closed and returns it. Processes started by the generator may keep
+    /// the output open after it has exited, so at `deadline` whatever has been read so far is returned.
+    /// Without a deadline this blocks until the output is closed.
+    fn finish(self, deadline: Option<Instant>) -> String {
+        match deadline {
+            Some(deadline) => while !self.reader.is_finished() && Instant::now() < deadline {
+                std::thread::sleep(Duration::from_millis(10));
+            },
+            None => self.reader.join().expect("Generator output thread panicked.")
+        }
+        let buf = self.buf.lock().expect("Generator output thread panicked.");
+        String::from_utf8_lossy(&buf).into_owned()
     }
-}
\ No newline at end of file
+}
+
+/// Reads all of a generator's output on another thread.
+fn capture<R: Read + Send + 'static>(mut output: R) -> Capture {
+    let buf = Arc::new(Mutex::new(vec![]));
+    let shared = buf.clone();
+    let reader = std::thread::spawn(move || {
+        let mut chunk = [0; 4096];
+        // Whatever was read before an error is still worth reporting.
+        while let Ok(n @ 1..) = output.read(&mut chunk) {
+            shared.lock().expect("Generator output lock poisoned.").extend_from_slice(&chunk[..n]);
+        }
+    });
+    Capture { buf, reader }
+}
+
+/// Waits for the generator to connect, sends it the request and reads back its response.
+fn exchange(socket: &TcpListener, request: &str, process: &mut Child, deadline: Option<Instant>) -> std::io::Result<String> {
+    let mut stream = loop {
+        let cmd_result = process.try_wait()?;
+        if let Some(e) = cmd_result {
+            return std::io::Result::Err(std::io::Error::other(
+                format!("Generator exited before connecting with status: {}", e)
+            ));
+        }
+
+        match socket.accept() {
+            Ok((stream, _)) => break stream,
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    return Err(timed_out());
+                }
+                std::thread::sleep(Duration::from_millis(50))
+            },
+            Err(e) => return Err(e)
+        }
+    };
+
+    let remaining = || match deadline {
+        Some(deadline) => deadline.checked_duration_since(Instant::now()).filter(|d| !d.is_zero()).map(Some).ok_or_else(timed_out),
+        None => Ok(None)
+    };
+
+    stream.set_nonblocking(false)?;
+    stream.set_write_timeout(remaining()?)?;
+    stream.write_all(request.as_bytes())?;
+    stream.shutdown(Shutdown::Write)?;
+
+    let mut response = String::new();
+    stream.set_read_timeout(remaining()?)?;
+    stream.read_to_string(&mut response).map_err(|e| match e.kind() {
+        ErrorKind::WouldBlock | ErrorKind::TimedOut => timed_out(),
+        _ => e
+    })?;
+
+    Ok(response)
+}
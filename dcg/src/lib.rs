@@ -0,0 +1,320 @@
+//! Runs dcg from Rust code, e.g. a `build.rs`:
+//!
+//! ```no_run
+//! dcg::Dcg::new()
+//!     .file("schema/types.dcg")
+//!     .include_path("schema")
+//!     .generator("rust")
+//!     .out_dir(std::env::var("OUT_DIR").unwrap())
+//!     .run()
+//!     .unwrap();
+//! ```
+
+pub mod error;
+pub mod generator;
+pub mod loader;
+mod output;
+pub mod report;
+
+use std::{collections::BTreeMap, fmt::Display, path::PathBuf, sync::mpsc, time::{Duration, Instant}};
+
+use app_common::{options::Options, protocol::{Feature, Response, Severity}};
+use ast::Root;
+
+pub use error::DcgError;
+pub use generator::{CodeGenerator, Protocol};
+pub use report::{Level, Log, Report, Status};
+
+/// How a generator is found.
+enum Backend {
+    /// Found by name as described by `generator::find`.
+    Named,
+
+    /// Given to `Dcg::code_generator`.
+    InProcess(Box<dyn CodeGenerator>)
+}
+
+/// A `Backend` that is ready to run.
+enum Runner<'a> {
+    Owned(Box<dyn CodeGenerator>),
+    Borrowed(&'a dyn CodeGenerator)
+}
+
+impl Runner<'_> {
+    fn generator(&self) -> &dyn CodeGenerator {
+        match self {
+            Runner::Owned(gen) => gen.as_ref(),
+            Runner::Borrowed(gen) => *gen
+        }
+    }
+}
+
+/// The input files, generators and settings of one dcg run.
+pub struct Dcg {
+    files: Vec<PathBuf>,
+    include_paths: Vec<PathBuf>,
+    generators: Vec<(String, Backend)>,
+
+    /// Used by generators without an entry in `out_dirs`.
+    out_dir: Option<PathBuf>,
+    out_dirs: BTreeMap<String, PathBuf>,
+
+    /// Generator name to that generator's options.
+    options: BTreeMap<String, BTreeMap<String, String>>,
+    protocol: Protocol,
+    timeout: Option<Duration>,
+    dry_run: bool
+}
+
+impl Default for Dcg {
+    fn default() -> Self {
+        Dcg::new()
+    }
+}
+
+impl Dcg {
+    pub fn new() -> Dcg {
+        Dcg {
+            files: vec![],
+            include_paths: vec![],
+            generators: vec![],
+            out_dir: None,
+            out_dirs: BTreeMap::new(),
+            options: BTreeMap::new(),
+            protocol: Protocol::Stdio,
+            timeout: None,
+            dry_run: false
+        }
+    }
+
+    pub fn file<P: Into<PathBuf>>(mut self, path: P) -> Dcg {
+        self.files.push(path.into());
+        self
+    }
+
+    /// Adds a directory to search for imported files.
+    pub fn include_path<P: Into<PathBuf>>(mut self, path: P) -> Dcg {
+        self.include_paths.push(path.into());
+        self
+    }
+
+    /// Adds a generator by name: a path to an executable, a built-in generator (`rust` or `cpp`) or the
+    /// suffix of a `dcg-<suffix>` executable.
+    pub fn generator<S: Into<String>>(mut self, name: S) -> Dcg {
+        self.generators.push((name.into(), Backend::Named));
+        self
+    }
+
+    /// Adds a generator that runs in this process.
+    pub fn code_generator<S: Into<String>, G: CodeGenerator + 'static>(mut self, name: S, generator: G) -> Dcg {
+        self.generators.push((name.into(), Backend::InProcess(Box::new(generator))));
+        self
+    }
+
+    /// Sets the output directory of generators without their own.
+    pub fn out_dir<P: Into<PathBuf>>(mut self, path: P) -> Dcg {
+        self.out_dir = Some(path.into());
+        self
+    }
+
+    /// Sets the output directory of one generator.
+    pub fn generator_out_dir<S: Into<String>, P: Into<PathBuf>>(mut self, generator: S, path: P) -> Dcg {
+        self.out_dirs.insert(generator.into(), path.into());
+        self
+    }
+
+    pub fn option<G: Into<String>, K: Into<String>, V: Into<String>>(mut self, generator: G, key: K, value: V) -> Dcg {
+        self.options.entry(generator.into()).or_default().insert(key.into(), value.into());
+        self
+    }
+
+    /// Sets how the AST is passed to external generators.
+    pub fn protocol(mut self, protocol: Protocol) -> Dcg {
+        self.protocol = protocol;
+        self
+    }
+
+    /// Kills external generators that run for longer than `timeout`. Generators that run inside dcg
+    /// can't be stopped, so they aren't limited.
+    pub fn timeout(mut self, timeout: Duration) -> Dcg {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Runs generators without writing their files.
+    pub fn dry_run(mut self, dry_run: bool) -> Dcg {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// The input files, without their imports.
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    fn has_generator(&self, name: &str) -> bool {
+        self.generators.iter().any(|(gen, _)| gen == name)
+    }
+
+    fn out_dir_for(&self, name: &str) -> Option<&PathBuf> {
+        self.out_dirs.get(name).or(self.out_dir.as_ref())
+    }
+
+    /// Checks the settings before anything is run.
+    fn check(&self) -> Result<(), DcgError> {
+        if self.files.is_empty() {
+            return Err("No input files specified.".into());
+        }
+        if self.generators.is_empty() {
+            return Err("No generators specified.".into());
+        }
+        if let Some(gen) = self.out_dirs.keys().find(|gen| !self.has_generator(gen)) {
+            return Err(DcgError::Argument(format!("Output directory given for \"{}\", which is not one of the generators.", gen)));
+        }
+        if let Some(gen) = self.options.keys().find(|gen| !self.has_generator(gen)) {
+            return Err(DcgError::Argument(format!("Option given for \"{}\", which is not one of the generators.", gen)));
+        }
+        for (gen, _) in &self.generators {
+            match self.out_dir_for(gen) {
+                Some(dir) if dir.exists() && !dir.is_dir() =>
+                    return Err(DcgError::Argument(format!("Output directory {:?} is not a directory.", dir))),
+                Some(_) => {},
+                None => return Err(DcgError::Argument(format!("No output directory for \"{}\".", gen)))
+            }
+        }
+        Ok(())
+    }
+
+    /// Loads, parses and resolves the input files and their imports.
+    pub fn parse(&self) -> Result<(Vec<loader::Source>, Root), DcgError> {
+        self.check()?;
+        let (sources, ast) = loader::load(&self.files, &self.include_paths)?;
+        Ok((sources, ast::resolve(ast)?))
+    }
+
+    /// Runs every generator on `ast` concurrently and writes their files. `on_report` is called as
+    /// each generator finishes. Reports are returned in the order generators were added.
+    pub fn generate<F: FnMut(&Report)>(&self, ast: &Root, mut on_report: F) -> Result<Vec<Report>, DcgError> {
+        self.check()?;
+
+        let runners = self.generators.iter()
+            .map(|(name, backend)| match backend {
+                Backend::Named => {
+                    let output_dir = self.out_dir_for(name).expect("Checked before generators are run.");
+                    generator::find(name, self.protocol, self.timeout, output_dir)
+                        .map(|gen| (name, Runner::Owned(gen)))
+                },
+                Backend::InProcess(gen) => Ok((name, Runner::Borrowed(gen.as_ref())))
+            })
+            .collect::<Result<Vec<_>, DcgError>>()?;
+
+        let (sender, receiver) = mpsc::channel();
+        let mut reports = std::thread::scope(|scope| {
+            for (name, runner) in &runners {
+                let sender = sender.clone();
+                scope.spawn(move || sender.send(self.run_generator(name, runner, ast)));
+            }
+            drop(sender);
+
+            receiver.iter()
+                .inspect(&mut on_report)
+                .collect::<Vec<Report>>()
+        });
+
+        reports.sort_by_key(|r| self.generators.iter().position(|(gen, _)| *gen == r.generator));
+        if reports.iter().any(|r| r.status != Status::Ok) {
+            return Err(DcgError::PluginFailed(reports));
+        }
+        Ok(reports)
+    }
+
+    /// Parses the input files and runs every generator on them.
+    pub fn run(&self) -> Result<Vec<Report>, DcgError> {
+        let (_, ast) = self.parse()?;
+        self.generate(&ast, |_| {})
+    }
+
+    /// Runs one generator and writes its files.
+    fn run_generator(&self, name: &str, runner: &Runner, ast: &Root) -> Report {
+        let start = Instant::now();
+        let mut report = Report {
+            generator: name.to_string(),
+            status: Status::Failed,
+            files: 0,
+            elapsed: Duration::default(),
+            log: Log::default()
+        };
+
+        let gen = runner.generator();
+        let unsupported = Feature::used_by(ast).into_iter()
+            .filter(|feature| !gen.features().contains(feature))
+            .map(|feature| format!("{:?}", feature))
+            .collect::<Vec<String>>();
+        if !unsupported.is_empty() {
+            report.log.error(format!("Error: Generator doesn't support features used by the input: {}", unsupported.join(", ")));
+            return report;
+        }
+
+        let output_dir = self.out_dir_for(name).expect("Checked before generators are run.");
+        let options = self.options.get(name).cloned().unwrap_or_default();
+        let response = gen.generate(ast, &Options::new(options))
+            .unwrap_or_else(|e| Response::from_error(e.to_string()));
+        report.elapsed = start.elapsed();
+
+        for d in &response.diagnostics {
+            match d.severity {
+                Severity::Error => report.log.error(format!("{:?}: {}", d.severity, d.message)),
+                Severity::Warning => report.log.info(format!("{:?}: {}", d.severity, d.message)),
+                Severity::Info => report.log.debug(d.message.clone())
+            }
+        }
+        if response.has_errors() {
+            if response.timed_out {
+                report.status = Status::TimedOut;
+            }
+            report.log.error("Error: Generator reported errors, no files were written.".to_string());
+            return report;
+        }
+
+        match output::write_files(output_dir, &response.files, self.dry_run, &mut report.log) {
+            Ok(()) => {
+                report.status = Status::Ok;
+                report.files = response.files.len();
+            },
+            Err(e) => report.log.error(format!("Error: {}", e))
+        }
+        report
+    }
+}
+
+impl Display for Dcg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let generators = self.generators.iter().map(|(gen, _)| gen).collect::<Vec<&String>>();
+        f.write_fmt(format_args!("      Generators: {:?}\n", generators))?;
+        for gen in generators {
+            if let Some(dir) = self.out_dir_for(gen) {
+                f.write_fmt(format_args!("Output Directory: {}={:?}\n", gen, dir))?;
+            }
+        }
+        f.write_fmt(format_args!("        Protocol: {:?}\n", self.protocol))?;
+        if let Some(timeout) = self.timeout {
+            f.write_fmt(format_args!("         Timeout: {:?}\n", timeout))?;
+        }
+
+        for file in &self.files {
+            f.write_fmt(format_args!("           Input: {:?}\n", file))?;
+        }
+
+        for path in &self.include_paths {
+            f.write_fmt(format_args!("    Include Path: {:?}\n", path))?;
+        }
+
+        for (gen, options) in &self.options {
+            for (key, value) in options {
+                f.write_fmt(format_args!("          Option: {}:{}={}\n", gen, key, value))?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,191 @@
+use std::{collections::HashMap, fmt::Display, path::{Path, PathBuf}};
+
+use ast::{File, Import, Root};
+use parser::ParseDiagnostic;
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(PathBuf, std::io::Error),
+    Parse(ParseDiagnostic),
+    ImportNotFound {
+        import: String,
+        from: PathBuf
+    },
+    Cycle(Vec<PathBuf>)
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            LoadError::Parse(d) => write!(f, "{}", d),
+            LoadError::ImportNotFound { import, from } =>
+                write!(f, "{}: Unable to find import \"{}\"", from.display(), import),
+            LoadError::Cycle(paths) => write!(
+                f,
+                "Import cycle: {}",
+                paths.iter().map(|p| p.display().to_string()).collect::<Vec<String>>().join(" -> ")
+            )
+        }
+    }
+}
+
+/// A loaded input file.
+pub struct Source {
+    pub path: PathBuf,
+    pub contents: String
+}
+
+struct Loader<'a> {
+    search_paths: &'a [PathBuf],
+    sources: Vec<Source>,
+    files: Vec<File>,
+    indices: HashMap<PathBuf, usize>,
+    imports: Vec<Vec<usize>>,
+    errors: Vec<LoadError>
+}
+
+fn namespace_path(import: &Import) -> PathBuf {
+    match import {
+        Import::Namespace(name) => {
+            let mut path = name.names().iter().map(|n| n.to_string()).collect::<PathBuf>();
+            path.set_extension("dcg");
+            path
+        },
+        Import::Path(path) => path.clone()
+    }
+}
+
+fn import_name(import: &Import) -> String {
+    match import {
+        Import::Namespace(name) => name.to_string(),
+        Import::Path(path) => path.display().to_string()
+    }
+}
+
+impl Loader<'_> {
+    /// Finds an import relative to the importing file, then relative to each search path.
+    fn find(&self, import: &Import, from: &Path) -> Option<PathBuf> {
+        let relative = namespace_path(import);
+        from.parent()
+            .map(|dir| dir.join(&relative))
+            .into_iter()
+            .chain(self.search_paths.iter().map(|dir| dir.join(&relative)))
+            .find(|path| path.is_file())
+    }
+
+    /// Reads and parses `path` unless it has already been loaded, returning its index.
+    fn load(&mut self, path: &Path, external: bool) -> Option<usize> {
+        let canonical = match path.canonicalize() {
+            Ok(canonical) => canonical,
+            Err(e) => {
+                self.errors.push(LoadError::Io(path.to_path_buf(), e));
+                return None;
+            }
+        };
+        if let Some(index) = self.indices.get(&canonical) {
+            return Some(*index);
+        }
+
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) => {
+                self.errors.push(LoadError::Io(path.to_path_buf(), e));
+                return None;
+            }
+        };
+
+        let index = self.files.len();
+        let mut file = match parser::parse_file(path, &contents, index) {
+            Ok(file) => file,
+            Err(d) => {
+                self.errors.push(LoadError::Parse(d));
+                return None;
+            }
+        };
+        file.set_external(external);
+
+        self.indices.insert(canonical, index);
+        self.sources.push(Source { path: path.to_path_buf(), contents });
+        self.files.push(file);
+        self.imports.push(vec![]);
+        Some(index)
+    }
+
+    fn load_imports(&mut self, index: usize) {
+        let from = self.sources[index].path.clone();
+        for import in self.files[index].imports().clone() {
+            let imported = match self.find(&import, &from) {
+                Some(path) => self.load(&path, true),
+                None => {
+                    self.errors.push(LoadError::ImportNotFound { import: import_name(&import), from: from.clone() });
+                    None
+                }
+            };
+            self.imports[index].extend(imported);
+        }
+    }
+
+    /// Depth first search for a cycle through `index`, returning the files on the cycle.
+    fn find_cycle(&self, index: usize, stack: &mut Vec<usize>, done: &mut Vec<bool>) -> Option<Vec<PathBuf>> {
+        if let Some(start) = stack.iter().position(|i| *i == index) {
+            return Some(
+                stack[start..].iter()
+                    .chain(std::iter::once(&index))
+                    .map(|i| self.sources[*i].path.clone())
+                    .collect()
+            );
+        }
+        if done[index] {
+            return None;
+        }
+
+        stack.push(index);
+        for imported in &self.imports[index] {
+            if let Some(cycle) = self.find_cycle(*imported, stack, done) {
+                return Some(cycle);
+            }
+        }
+        stack.pop();
+        done[index] = true;
+        None
+    }
+}
+
+/// Loads `files` and everything they import, transitively. Files only reached through an import
+/// are marked as external.
+pub fn load(files: &[PathBuf], search_paths: &[PathBuf]) -> Result<(Vec<Source>, Root), Vec<LoadError>> {
+    let mut loader = Loader {
+        search_paths,
+        sources: vec![],
+        files: vec![],
+        indices: HashMap::new(),
+        imports: vec![],
+        errors: vec![]
+    };
+
+    for file in files {
+        loader.load(file, false);
+    }
+
+    // Imported files are appended as they are found, so this also visits them.
+    let mut index = 0;
+    while index < loader.files.len() {
+        loader.load_imports(index);
+        index += 1;
+    }
+
+    let mut done = vec![false; loader.files.len()];
+    for index in 0..loader.files.len() {
+        if let Some(cycle) = loader.find_cycle(index, &mut vec![], &mut done) {
+            loader.errors.push(LoadError::Cycle(cycle));
+            break;
+        }
+    }
+
+    if !loader.errors.is_empty() {
+        return Err(loader.errors);
+    }
+
+    Ok((loader.sources, Root::new(loader.files, ast::Context::new())))
+}
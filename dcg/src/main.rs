@@ -1,61 +1,88 @@
-mod generator;
-
-use std::{fmt::Display, path::PathBuf};
-use clap::{App, Arg};
+use std::{path::{Path, PathBuf}, time::Duration};
+use clap::{App, Arg, ErrorKind as ClapErrorKind};
+use dcg::{Dcg, DcgError, Level, Protocol};
 use parser::Rule;
 use pest::{Parser};
 
 struct AppConfig {
-    generators: Vec<String>,
-    output_dir: PathBuf,
-    files: Vec<PathBuf>,
+    dcg: Dcg,
+    verbosity: Level,
     debug_parse: bool,
-    debug_ast: bool
-}
-
-#[derive(Debug)]
-enum ArgError {
-    Msg(String)
+    debug_ast: bool,
+    debug_json: bool
 }
 
-impl From<&str> for ArgError {
-    fn from(msg: &str) -> Self {
-        ArgError::Msg(String::from(msg))
+/// Parses `<generator>=<dir>`.
+fn parse_output_dir(out: &str) -> Result<(String, PathBuf), DcgError> {
+    match out.split_once('=') {
+        Some((gen, dir)) if !gen.is_empty() && !dir.is_empty() => Ok((gen.to_string(), PathBuf::from(dir))),
+        _ => Err(DcgError::Argument(format!("Invalid output directory \"{}\", expected <generator>=<dir>.", out)))
     }
 }
 
-impl Display for AppConfig {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_fmt(format_args!("      Generators: {:?}\n", self.generators))?;
-        f.write_fmt(format_args!("Output Directory: {:?}\n", self.output_dir))?;
-
-        for file in &self.files {
-            f.write_fmt(format_args!("           Input: {:?}\n", file))?;
-        }
-
-        Ok(())
+/// Parses `<generator>:<key>=<value>`.
+fn parse_option(opt: &str) -> Result<(String, String, String), DcgError> {
+    let invalid = || DcgError::Argument(format!("Invalid option \"{}\", expected <generator>:<key>=<value>.", opt));
+    let (gen, option) = opt.split_once(':').ok_or_else(invalid)?;
+    let (key, value) = option.split_once('=').ok_or_else(invalid)?;
+    if gen.is_empty() || key.is_empty() {
+        return Err(invalid());
     }
+    Ok((gen.to_string(), key.to_string(), value.to_string()))
 }
 
-fn process_args(args: App) -> Result<AppConfig, ArgError> {
-    let args = args.get_matches();
+fn process_args(args: App) -> Result<AppConfig, DcgError> {
+    let args = args.get_matches_safe().map_err(|e| match e.kind {
+        ClapErrorKind::HelpDisplayed | ClapErrorKind::VersionDisplayed => e.exit(),
+        _ => DcgError::Argument(e.message.trim_start_matches("error: ").to_string())
+    })?;
 
     let generators = args.values_of("generator").ok_or("No generators specified.")?;
-    let output_dir = args.value_of("output").ok_or("No output dir specified.")?;
     let files = args.values_of("FILE").ok_or("No input files specified.")?;
-    let debug_parse = args.is_present("debug-parse");
-    let debug_ast = args.is_present("debug-ast");
+    let protocol = args.value_of("protocol").unwrap_or("stdio");
+    let protocol = Protocol::from(protocol).ok_or("Unknown protocol.")?;
+
+    let mut dcg = Dcg::new()
+        .protocol(protocol)
+        .dry_run(args.is_present("dry-run"));
+
+    for gen in generators {
+        dcg = dcg.generator(gen);
+    }
+    for file in files {
+        dcg = dcg.file(file);
+    }
+    for path in args.values_of("include").into_iter().flatten() {
+        dcg = dcg.include_path(path);
+    }
+    if let Some(dir) = args.value_of("output") {
+        dcg = dcg.out_dir(dir);
+    }
+    for out in args.values_of("out").into_iter().flatten() {
+        let (gen, dir) = parse_output_dir(out)?;
+        dcg = dcg.generator_out_dir(gen, dir);
+    }
+    for opt in args.values_of("opt").into_iter().flatten() {
+        let (gen, key, value) = parse_option(opt)?;
+        dcg = dcg.option(gen, key, value);
+    }
+    if let Some(secs) = args.value_of("timeout") {
+        let secs = secs.parse::<u64>().map_err(|_| "Timeout must be a whole number of seconds.")?;
+        dcg = dcg.timeout(Duration::from_secs(secs));
+    }
 
-    let generators = generators.map(String::from).collect();
-    let output_dir = PathBuf::from(output_dir);
-    let files = files.map(PathBuf::from).collect();
+    let verbosity = match (args.is_present("quiet"), args.is_present("verbose")) {
+        (true, _) => Level::Error,
+        (false, true) => Level::Debug,
+        (false, false) => Level::Info
+    };
 
     Ok(AppConfig {
-        generators,
-        output_dir,
-        files,
-        debug_parse,
-        debug_ast
+        dcg,
+        verbosity,
+        debug_parse: args.is_present("debug-parse"),
+        debug_ast: args.is_present("debug-ast"),
+        debug_json: args.is_present("debug-json")
     })
 }
 
@@ -64,14 +91,29 @@ fn main() {
     let app = App::new("dcg")
         .version("0.1")
         .about("DataClass Generator")
+        .after_help("EXIT CODES:
+    0    Success.
+    1    A generator failed or timed out.
+    2    Invalid arguments.
+    3    An input file or import couldn't be read.
+    4    An input file has syntax errors.
+    5    An input file refers to unknown types or uses them incorrectly.
+    6    A generator couldn't be found.")
         .arg(
             Arg::with_name("generator")
                 .long("generator")
                 .short("g")
-                .long_help("Comma-separated list of generation executables. Must either be a path to an executable or \
-the suffix part of a \"dcg-<suffix>\" executable that can be resolved based on the standard path.
+                .long_help("Comma-separated list of generators. Each must either be a path to an executable, one of the \
+generators built into dcg (\"rust\" or \"cpp\") or the suffix part of a \"dcg-<suffix>\" executable that can be \
+resolved based on the standard path. Built-in generators run inside dcg and ignore --protocol.
 
-The executable should take as arguments a port and the output dir. The JSON-ified AST will be written to the port.
+The executable should take as arguments a port and the output dir. With the default stdio protocol the port is \"-\", \
+the JSON-ified AST is written to the executable's stdin and a JSON response is read from its stdout. With the tcp \
+protocol the JSON-ified AST will be written to the port and the response read back from it. The response lists the \
+files to generate, relative to the output dir, which dcg then writes.
+
+Before running it, dcg calls the executable with \"--capabilities\" and refuses to use it if its protocol version \
+differs or it lacks a language feature used by the input.
 ")
                 .required(true)
                 .takes_value(true)
@@ -84,11 +126,74 @@ The executable should take as arguments a port and the output dir. The JSON-ifie
                 .long("output")
                 .short("o")
                 .value_name("OUTPUT_DIR")
-                .help("Path to the output directory.")
-                .required(true)
+                .help("Path to the output directory of generators without an --out directory.")
                 .takes_value(true)
                 .multiple(false)
         )
+        .arg(
+            Arg::with_name("out")
+                .long("out")
+                .value_name("GEN=DIR")
+                .help("Output directory for one generator, e.g. \"rust=src/gen\". May be given more than once.")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .use_delimiter(false)
+        )
+        .arg(
+            Arg::with_name("protocol")
+                .long("protocol")
+                .value_name("PROTOCOL")
+                .help("How the AST is passed to generators.")
+                .takes_value(true)
+                .possible_values(&["stdio", "tcp"])
+                .default_value("stdio")
+        )
+        .arg(
+            Arg::with_name("include")
+                .long("include")
+                .short("I")
+                .value_name("DIR")
+                .help("Directory to search for imported files. May be given more than once.")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+        )
+        .arg(
+            Arg::with_name("opt")
+                .long("opt")
+                .value_name("GEN:KEY=VALUE")
+                .help("Option passed to one generator, e.g. \"rust:derive=Clone,Debug\". May be given more than once.")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .use_delimiter(false)
+        )
+        .arg(
+            Arg::with_name("timeout")
+                .long("timeout")
+                .value_name("SECONDS")
+                .help("Kill external generators that run for longer than this. Built-in generators aren't limited.")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("dry-run")
+                .long("dry-run")
+                .help("List the files generators produce without writing them.")
+        )
+        .arg(
+            Arg::with_name("quiet")
+                .long("quiet")
+                .short("q")
+                .help("Only print errors.")
+                .conflicts_with("verbose")
+        )
+        .arg(
+            Arg::with_name("verbose")
+                .long("verbose")
+                .short("v")
+                .help("Also print the configuration and everything generators print.")
+        )
         .arg(
             Arg::with_name("debug-parse")
                 .long("debug-parse")
@@ -99,6 +204,11 @@ The executable should take as arguments a port and the output dir. The JSON-ifie
                 .long("debug-ast")
                 .help("Display debug AST.")
         )
+        .arg(
+            Arg::with_name("debug-json")
+                .long("debug-json")
+                .help("Display the JSON AST sent to generators.")
+        )
         .arg(
             Arg::with_name("FILE")
                 .help("Path to an input file.")
@@ -108,49 +218,73 @@ The executable should take as arguments a port and the output dir. The JSON-ifie
 
     let config = match process_args(app) {
         Ok(config) => config,
-        Err(e) => {
-            println!("{:?}", e);
-            std::process::exit(1)
-        }
+        Err(e) => exit_with(e)
     };
 
-    println!("{}", config);
-
-    let file_contents = {
-        let mut file_contents = vec![];
-        for file in config.files {
-            let result = std::fs::read_to_string(file);
-            match result {
-                Ok(result) => file_contents.push(result),
-                Err(e) => {
-                    println!("Error: {:?}", e);
-                    std::process::exit(1);
-                }
+    if let Err(e) = run(&config) {
+        exit_with(e);
+    }
+}
+
+fn exit_with(e: DcgError) -> ! {
+    match e {
+        DcgError::Io(_) | DcgError::Parse(_) | DcgError::Semantic(_) => {
+            for line in e.to_string().lines() {
+                eprintln!("Error: {}", line);
             }
-        }
-        file_contents
-    };
+        },
+        DcgError::Argument(_) | DcgError::PluginNotFound(_) | DcgError::PluginFailed(_) => eprintln!("Error: {}", e)
+    }
+    std::process::exit(e.exit_code())
+}
+
+/// Prints the parse tree of `contents` to stderr, or the syntax error if it doesn't parse.
+fn print_parse_tree(path: &Path, contents: &str) {
+    match parser::RawParser::parse(Rule::file, contents) {
+        Ok(pairs) => app_common::tree_format::display_debug_parse_tree(&pairs),
+        Err(e) => eprintln!("{}", e.with_path(&path.to_string_lossy()))
+    }
+}
+
+fn run(config: &AppConfig) -> Result<(), DcgError> {
+    if config.verbosity >= Level::Debug {
+        println!("{}", config.dcg);
+    }
+
+    let parsed = config.dcg.parse();
 
     if config.debug_parse {
-        println!("Debug Parse Tree:");
-        file_contents.iter().for_each(|f| {
-            let pairs = parser::RawParser::parse(Rule::file, f)
-                .unwrap_or_else(|e| panic!("{}", e));
-            app_common::tree_format::display_debug_parse_tree(&pairs)
-        });
+        eprintln!("Debug Parse Tree:");
+        match &parsed {
+            Ok((sources, _)) => sources.iter()
+                .for_each(|source| print_parse_tree(&source.path, &source.contents)),
+            // Parsing or resolving failed, so dump whatever the input files themselves parse to. Files
+            // that can't be read are reported with the error.
+            Err(_) => config.dcg.files().iter()
+                .filter_map(|path| std::fs::read_to_string(path).ok().map(|contents| (path, contents)))
+                .for_each(|(path, contents)| print_parse_tree(path, &contents))
+        }
     }
 
-    let ast = parser::parse(&file_contents);
+    let (_, ast) = parsed?;
+
     if config.debug_ast {
-        println!("Debug AST:");
+        eprintln!("Debug AST:");
         app_common::tree_format::display_debug_ast(&ast);
     }
+    if config.debug_json {
+        println!("{}", serde_json::to_string_pretty(&ast).expect("The AST is serializable."));
+    }
 
-    println!("{}", serde_json::to_string_pretty(&ast).unwrap());
+    // Print each generator's output as soon as it finishes so that the output of concurrent
+    // generators isn't interleaved.
+    let result = config.dcg.generate(&ast, |report| report.print_log(config.verbosity));
 
-    for gen in &config.generators {
-        if let Err(e) = generator::Generator::from(gen).run(&ast, &config.output_dir) {
-            println!("Error: {}", e);
+    if config.verbosity >= Level::Info {
+        match &result {
+            Ok(reports) | Err(DcgError::PluginFailed(reports)) => dcg::report::print_summary(reports),
+            Err(_) => {}
         }
     }
+    result.map(|_| ())
 }
@@ -0,0 +1,42 @@
+use std::path::{Component, Path};
+
+use app_common::protocol::OutputFile;
+
+use crate::report::Log;
+
+/// Returns an error for paths that could end up outside of the output directory.
+fn check_path(path: &Path) -> Result<(), String> {
+    let escapes = path.components().any(|c| match c {
+        Component::Normal(_) | Component::CurDir => false,
+        Component::ParentDir | Component::RootDir | Component::Prefix(_) => true
+    });
+
+    if escapes || path.as_os_str().is_empty() {
+        return Err(format!("Refusing to write outside of the output directory: {:?}", path));
+    }
+    Ok(())
+}
+
+/// Checks every file before writing any of them so that a bad path doesn't leave partial output.
+/// With `dry_run` the files are listed instead of written.
+pub fn write_files(output_dir: &Path, files: &[OutputFile], dry_run: bool, log: &mut Log) -> Result<(), String> {
+    for file in files {
+        check_path(&file.path)?;
+    }
+
+    for file in files {
+        let path = output_dir.join(&file.path);
+        if dry_run {
+            log.info(format!("Would write: {:?} ({} bytes)", path, file.contents.len()));
+            continue;
+        }
+
+        log.info(format!("Writing: {:?}", path));
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("{:?}: {}", parent, e))?;
+        }
+        std::fs::write(&path, &file.contents).map_err(|e| format!("{:?}: {}", path, e))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use std::time::Duration;
+
+/// How much dcg prints. Each level includes the ones before it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Info,
+    Debug
+}
+
+/// Messages collected while running a generator, printed once it is done.
+#[derive(Debug, Default)]
+pub struct Log {
+    entries: Vec<(Level, String)>
+}
+
+impl Log {
+    pub fn error(&mut self, msg: String) {
+        self.entries.push((Level::Error, msg));
+    }
+
+    pub fn info(&mut self, msg: String) {
+        self.entries.push((Level::Info, msg));
+    }
+
+    pub fn debug(&mut self, msg: String) {
+        self.entries.push((Level::Debug, msg));
+    }
+}
+
+/// How a generator run ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    Failed,
+    TimedOut
+}
+
+/// The outcome of one generator, printed once it is done.
+#[derive(Debug)]
+pub struct Report {
+    pub generator: String,
+    pub status: Status,
+    pub files: usize,
+    pub elapsed: Duration,
+
+    /// Everything dcg and the generator printed while it ran.
+    pub log: Log
+}
+
+impl Report {
+    /// Prints the messages at or below `verbosity`. Errors go to stderr.
+    pub fn print_log(&self, verbosity: Level) {
+        // The generator's own output is most useful when it failed.
+        let verbosity = match (self.status, verbosity) {
+            (Status::Ok, _) | (_, Level::Error) => verbosity,
+            (Status::Failed, _) | (Status::TimedOut, _) => Level::Debug
+        };
+        for (level, line) in self.log.entries.iter().filter(|(level, _)| *level <= verbosity) {
+            match level {
+                Level::Error => eprintln!("[{}] {}", self.generator, line),
+                Level::Info | Level::Debug => println!("[{}] {}", self.generator, line)
+            }
+        }
+    }
+}
+
+pub fn print_summary(reports: &[Report]) {
+    let width = reports.iter().map(|r| r.generator.len()).chain(Some("Generator".len())).max().unwrap_or(0);
+
+    println!();
+    println!("{:<width$}  {:<9}  {:>5}  {:>8}", "Generator", "Status", "Files", "Time", width = width);
+    for report in reports {
+        let status = match report.status {
+            Status::Ok => "ok",
+            Status::Failed => "failed",
+            Status::TimedOut => "timed out"
+        };
+        println!(
+            "{:<width$}  {:<9}  {:>5}  {:>7.2}s",
+            report.generator, status, report.files, report.elapsed.as_secs_f64(), width = width
+        );
+    }
+}
@@ -0,0 +1,178 @@
+mod common;
+
+use std::{path::PathBuf, process::{Command, Output}};
+
+use common::dir;
+
+fn dcg(dir: &PathBuf, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_dcg"))
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+const SCHEMA: &str = "namespace app\nstruct User { id: Int32 }\n";
+
+#[test]
+fn succeeds() {
+    let dir = dir("succeeds", &[("a.dcg", SCHEMA)]);
+    let output = dcg(&dir, &["a.dcg", "-g", "rust,cpp", "-o", "out"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(dir.join("out/app/mod.rs").exists());
+}
+
+#[test]
+fn prints_nothing_when_quiet() {
+    let dir = dir("quiet", &[("a.dcg", SCHEMA)]);
+    let output = dcg(&dir, &["a.dcg", "-g", "rust", "-o", "out", "-q"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "");
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+}
+
+#[test]
+fn prints_configuration_when_verbose() {
+    let dir = dir("verbose", &[("a.dcg", SCHEMA)]);
+    let output = dcg(&dir, &["a.dcg", "-g", "rust", "-o", "out", "-v"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("      Generators: [\"rust\"]\n"));
+    assert!(stdout.contains("[rust] Writing: \"out/mod.rs\"\n"));
+    assert!(!stdout.contains("\"namespace\""));
+}
+
+#[test]
+fn prints_the_ast() {
+    let dir = dir("debug_ast", &[("a.dcg", "namespace app\nstruct S { nick?: String\n age?: Int32 = 3 }\n")]);
+    let output = dcg(&dir, &["a.dcg", "-g", "rust", "-o", "out", "-q", "--debug-ast"]);
+    let tree = String::from_utf8_lossy(&output.stderr);
+    assert!(tree.starts_with("Debug AST:\nRoot\n"));
+    assert!(tree.contains("+-Field(nick?)\n"));
+    assert!(tree.contains("+-Field(age? = 3)\n"));
+}
+
+#[test]
+fn prints_the_parse_tree_of_invalid_files() {
+    let dir = dir("debug_parse", &[
+        ("a.dcg", "namespace app\nstruct S { a: Int32 }\n"),
+        ("b.dcg", "namespace app\nstruct T { b Int32 }\n")
+    ]);
+    let output = dcg(&dir, &["a.dcg", "b.dcg", "-g", "rust", "-o", "out", "--debug-parse"]);
+    assert_eq!(output.status.code(), Some(4));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.starts_with("Debug Parse Tree:\nfile("));
+    assert!(stderr.contains("+-field(a: Int32 )\n"));
+    assert!(stderr.contains("--> b.dcg:2:14"));
+}
+
+#[test]
+fn runs_builtin_generators_with_a_timeout() {
+    let dir = dir("builtin_timeout", &[("a.dcg", SCHEMA)]);
+    let output = dcg(&dir, &["a.dcg", "-g", "rust,cpp", "-o", "out", "--timeout", "30"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(dir.join("out/app.hpp").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn reports_timed_out_generators() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let script = format!(
+        "#!/bin/sh\nif [ \"$1\" = \"--capabilities\" ]; then echo '{{\"protocol_version\":{},\"features\":[]}}'; exit 0; fi\nsleep 10\n",
+        app_common::protocol::PROTOCOL_VERSION
+    );
+    let dir = dir("timed_out", &[("a.dcg", SCHEMA), ("slow.sh", &script)]);
+    std::fs::set_permissions(dir.join("slow.sh"), std::fs::Permissions::from_mode(0o755)).unwrap();
+    let output = dcg(&dir, &["a.dcg", "-g", "./slow.sh", "-o", "out", "--timeout", "1"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("timed out"));
+}
+
+#[test]
+fn fails_on_missing_input() {
+    let dir = dir("missing_input", &[]);
+    assert_eq!(dcg(&dir, &["a.dcg", "-g", "rust", "-o", "out"]).status.code(), Some(3));
+}
+
+#[test]
+fn fails_on_syntax_errors() {
+    let dir = dir("syntax_errors", &[("a.dcg", "namespace app\nstruct User { id Int32 }\n")]);
+    assert_eq!(dcg(&dir, &["a.dcg", "-g", "rust", "-o", "out"]).status.code(), Some(4));
+}
+
+#[test]
+fn fails_on_unknown_types() {
+    let dir = dir("unknown_types", &[("a.dcg", "namespace app\nstruct User { id: Id }\n")]);
+    assert_eq!(dcg(&dir, &["a.dcg", "-g", "rust", "-o", "out"]).status.code(), Some(5));
+}
+
+#[test]
+fn fails_on_unknown_generators() {
+    let dir = dir("unknown_generators", &[("a.dcg", SCHEMA)]);
+    assert_eq!(dcg(&dir, &["a.dcg", "-g", "nonexistent", "-o", "out"]).status.code(), Some(6));
+}
+
+#[test]
+fn fails_on_invalid_options() {
+    let dir = dir("invalid_options", &[("a.dcg", SCHEMA)]);
+    assert_eq!(dcg(&dir, &["a.dcg", "-g", "rust", "-o", "out", "--opt", "derive=Debug"]).status.code(), Some(2));
+    assert_eq!(dcg(&dir, &["a.dcg", "-g", "rust", "-o", "out", "--opt", "cpp:std=17"]).status.code(), Some(2));
+}
+
+#[test]
+fn fails_on_unknown_options() {
+    let dir = dir("unknown_options", &[("a.dcg", SCHEMA)]);
+    let output = dcg(&dir, &["a.dcg", "-g", "rust", "-o", "out", "--opt", "rust:color=blue"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown option \"color\". Known options: derive, external"));
+}
+
+#[test]
+fn writes_each_generator_to_its_own_directory() {
+    let dir = dir("out_dirs", &[("a.dcg", SCHEMA)]);
+    let output = dcg(&dir, &["a.dcg", "-g", "rust,cpp", "-o", "shared", "--out", "rust=src/gen"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(dir.join("src/gen/app/mod.rs").exists());
+    assert!(dir.join("shared/app.hpp").exists());
+    assert!(!dir.join("shared/mod.rs").exists());
+}
+
+#[test]
+fn fails_on_invalid_output_directories() {
+    let dir = dir("invalid_out_dirs", &[("a.dcg", SCHEMA), ("file", "")]);
+    assert_eq!(dcg(&dir, &["a.dcg", "-g", "rust,cpp", "--out", "rust=src/gen"]).status.code(), Some(2));
+    assert_eq!(dcg(&dir, &["a.dcg", "-g", "rust", "-o", "out", "--out", "cpp=include"]).status.code(), Some(2));
+    assert_eq!(dcg(&dir, &["a.dcg", "-g", "rust", "--out", "rust=file"]).status.code(), Some(2));
+    assert!(!dir.join("src").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn reports_every_generator_in_order() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let script = |body: &str| format!(
+        "#!/bin/sh\nif [ \"$1\" = \"--capabilities\" ]; then echo '{{\"protocol_version\":{},\"features\":[]}}'; exit 0; fi\n{}\n",
+        app_common::protocol::PROTOCOL_VERSION, body
+    );
+    let dir = dir("parallel", &[
+        ("a.dcg", SCHEMA),
+        ("slow.sh", &script("sleep 1\necho '{\"files\":[{\"path\":\"slow.txt\",\"contents\":\"\"}]}'")),
+        ("broken.sh", &script("echo 'not json'"))
+    ]);
+    for name in ["slow.sh", "broken.sh"] {
+        std::fs::set_permissions(dir.join(name), std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    let output = dcg(&dir, &["a.dcg", "-g", "./slow.sh,./broken.sh,rust", "-o", "out"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(dir.join("out/slow.txt").exists());
+    assert!(dir.join("out/mod.rs").exists());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let summary = stdout.lines()
+        .skip_while(|line| !line.starts_with("Generator"))
+        .map(|line| line.split_whitespace().take(2).collect::<Vec<&str>>().join(" "))
+        .collect::<Vec<String>>();
+    assert_eq!(summary, vec!["Generator Status", "./slow.sh ok", "./broken.sh failed", "rust ok"]);
+}
@@ -0,0 +1,133 @@
+mod common;
+
+use std::{path::PathBuf, time::Duration};
+
+use app_common::{AppError, CodeGenerator, options::Options, protocol::{Feature, OutputFile, Response}};
+use ast::Root;
+use dcg::{Dcg, DcgError, Status};
+
+use common::dir;
+
+const SCHEMA: &str = "namespace app\nstruct User { id: Int32 }\n";
+
+#[test]
+fn runs_builtin_generators() {
+    let dir = dir("builtin", &[("a.dcg", SCHEMA)]);
+    let reports = Dcg::new()
+        .file(dir.join("a.dcg"))
+        .generator("rust")
+        .generator("cpp")
+        .out_dir(dir.join("out"))
+        .generator_out_dir("cpp", dir.join("include"))
+        .run()
+        .unwrap();
+    let summary = reports.iter().map(|r| (r.generator.as_str(), r.status, r.files)).collect::<Vec<_>>();
+    assert_eq!(summary, vec![("rust", Status::Ok, 2), ("cpp", Status::Ok, 1)]);
+    assert!(dir.join("out/app/mod.rs").exists());
+    assert!(dir.join("include/app.hpp").exists());
+}
+
+#[test]
+fn generates_the_example() {
+    let dir = dir("example", &[]);
+    let reports = Dcg::new()
+        .file(concat!(env!("CARGO_MANIFEST_DIR"), "/../example/foo.dcg"))
+        .generator("rust")
+        .generator("cpp")
+        .out_dir(dir.join("out"))
+        .dry_run(true)
+        .run()
+        .unwrap();
+    assert!(reports.iter().all(|r| r.status == Status::Ok));
+}
+
+#[test]
+fn writes_nothing_in_dry_runs() {
+    let dir = dir("dry_run", &[("a.dcg", SCHEMA)]);
+    let reports = Dcg::new().file(dir.join("a.dcg")).generator("rust").out_dir(dir.join("out")).dry_run(true).run().unwrap();
+    assert_eq!(reports[0].files, 2);
+    assert!(!dir.join("out").exists());
+}
+
+#[test]
+fn resolves_imports_from_include_paths() {
+    let dir = dir("include_paths", &[("a.dcg", "namespace app\nimport base\nstruct User { id: base.Id }\n")]);
+    std::fs::create_dir_all(dir.join("include")).unwrap();
+    std::fs::write(dir.join("include/base.dcg"), "namespace base\nnewtype Id = Int64\n").unwrap();
+    let dcg = Dcg::new().file(dir.join("a.dcg")).generator("rust").out_dir(dir.join("out"));
+    assert!(matches!(dcg.parse(), Err(DcgError::Io(_))));
+    let (sources, root) = dcg.include_path(dir.join("include")).parse().unwrap();
+    assert_eq!(sources.len(), 2);
+    assert!(root.files()[1].external());
+}
+
+#[test]
+fn returns_errors_by_kind() {
+    let dir = dir("errors", &[("a.dcg", SCHEMA), ("syntax.dcg", "namespace app\nstruct\n"), ("unknown.dcg", "namespace app\nstruct S { x: Missing }\n")]);
+    let run = |file: &str, generator: &str| Dcg::new().file(dir.join(file)).generator(generator).out_dir(dir.join("out")).run();
+    assert!(matches!(run("a.dcg", "nonexistent"), Err(DcgError::PluginNotFound(name)) if name == "nonexistent"));
+    assert!(matches!(run("missing.dcg", "rust"), Err(DcgError::Io(_))));
+    assert!(matches!(run("syntax.dcg", "rust"), Err(DcgError::Parse(_))));
+    assert!(matches!(run("unknown.dcg", "rust"), Err(DcgError::Semantic(_))));
+    assert!(matches!(Dcg::new().file(dir.join("a.dcg")).generator("rust").run(), Err(DcgError::Argument(_))));
+}
+
+/// Writes one file listing the namespaces of the input, each followed by the `suffix` option.
+struct Namespaces;
+
+impl CodeGenerator for Namespaces {
+    fn features(&self) -> &[Feature] {
+        &[]
+    }
+
+    fn generate(&self, ast: &Root, options: &Options) -> Result<Response, AppError> {
+        options.check(&["suffix"])?;
+        let contents = ast.files().iter()
+            .map(|f| format!("{}{}\n", f.namespace(), options.get("suffix").unwrap_or("")))
+            .collect::<String>();
+        Ok(Response::from_files(vec![OutputFile { path: PathBuf::from("namespaces.txt"), contents }]))
+    }
+}
+
+#[test]
+fn runs_in_process_generators() {
+    let dir = dir("in_process", &[("a.dcg", SCHEMA)]);
+    let dcg = Dcg::new()
+        .file(dir.join("a.dcg"))
+        .code_generator("namespaces", Namespaces)
+        .option("namespaces", "suffix", "!")
+        .out_dir(dir.join("out"));
+    let reports = dcg.run().unwrap();
+    assert_eq!(reports[0].status, Status::Ok);
+    assert_eq!(std::fs::read_to_string(dir.join("out/namespaces.txt")).unwrap(), "app!\n");
+
+    let reports = match dcg.option("namespaces", "prefix", "?").run() {
+        Err(DcgError::PluginFailed(reports)) => reports,
+        result => panic!("unexpected result {:?}", result.map(|_| ()))
+    };
+    assert_eq!(reports[0].status, Status::Failed);
+}
+
+#[test]
+fn refuses_input_with_unsupported_features() {
+    let dir = dir("unsupported", &[("a.dcg", "namespace app\nstruct User { tags: Set<String> }\n")]);
+    let result = Dcg::new().file(dir.join("a.dcg")).code_generator("namespaces", Namespaces).out_dir(dir.join("out")).run();
+    let Err(DcgError::PluginFailed(reports)) = result else {
+        panic!("generator ran");
+    };
+    assert_eq!(reports[0].status, Status::Failed);
+    assert!(!dir.join("out").exists());
+}
+
+#[test]
+fn runs_in_process_generators_without_a_timeout() {
+    let dir = dir("in_process_timeout", &[("a.dcg", SCHEMA)]);
+    let reports = Dcg::new()
+        .file(dir.join("a.dcg"))
+        .code_generator("namespaces", Namespaces)
+        .out_dir(dir.join("out"))
+        .timeout(Duration::from_secs(1))
+        .run()
+        .unwrap();
+    assert_eq!(reports[0].status, Status::Ok);
+}
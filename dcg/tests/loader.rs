@@ -0,0 +1,54 @@
+mod common;
+
+use std::path::PathBuf;
+
+use dcg::loader::{self, LoadError};
+
+use common::dir;
+
+#[test]
+fn loads_imports_transitively() {
+    let dir = dir("transitive", &[
+        ("main.dcg", "namespace app\nimport \"lib/a.dcg\"\nstruct S { a: a.A }\n"),
+        ("lib/a.dcg", "namespace a\nimport b.types\nstruct A { b: b.types.B }\n"),
+        ("include/b/types.dcg", "namespace b.types\nstruct B { x: Int32 }\n")
+    ]);
+    let (sources, root) = loader::load(&[dir.join("main.dcg")], &[dir.join("include")]).unwrap();
+    let paths = sources.iter().map(|s| s.path.strip_prefix(&dir).unwrap()).collect::<Vec<_>>();
+    assert_eq!(paths, vec![PathBuf::from("main.dcg"), PathBuf::from("lib/a.dcg"), PathBuf::from("include/b/types.dcg")]);
+    let external = root.files().iter().map(|f| *f.external()).collect::<Vec<bool>>();
+    assert_eq!(external, vec![false, true, true]);
+    assert!(ast::resolve(root).is_ok());
+}
+
+#[test]
+fn loads_shared_imports_once() {
+    let dir = dir("shared", &[
+        ("a.dcg", "namespace a\nimport c\n"),
+        ("b.dcg", "namespace b\nimport c\n"),
+        ("c.dcg", "namespace c\n")
+    ]);
+    let (sources, _) = loader::load(&[dir.join("a.dcg"), dir.join("b.dcg")], &[]).unwrap();
+    assert_eq!(sources.len(), 3);
+}
+
+#[test]
+fn rejects_import_cycles() {
+    let dir = dir("cycle", &[
+        ("a.dcg", "namespace a\nimport b\n"),
+        ("b.dcg", "namespace b\nimport a\n")
+    ]);
+    let errors = loader::load(&[dir.join("a.dcg")], &[]).err().unwrap();
+    let [LoadError::Cycle(paths)] = errors.as_slice() else {
+        panic!("unexpected errors {:?}", errors);
+    };
+    assert_eq!(paths, &vec![dir.join("a.dcg"), dir.join("b.dcg"), dir.join("a.dcg")]);
+}
+
+#[test]
+fn rejects_missing_imports() {
+    let dir = dir("missing", &[("a.dcg", "namespace a\nimport b\n")]);
+    let errors = loader::load(&[dir.join("a.dcg")], &[]).err().unwrap();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].to_string(), format!("{}: Unable to find import \"b\"", dir.join("a.dcg").display()));
+}
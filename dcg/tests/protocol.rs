@@ -0,0 +1,126 @@
+#![cfg(unix)]
+
+mod common;
+
+use std::{os::unix::fs::PermissionsExt, path::PathBuf, process::{Command, Output}};
+
+use app_common::protocol::{PROTOCOL_VERSION, Request};
+
+/// Writes `a.dcg` and a `gen.sh` generator into a fresh directory named `name` and returns its path.
+/// The generator prints `capabilities` when asked for them, and otherwise saves its request to
+/// `request.json` and prints `response`.
+fn dir(name: &str, schema: &str, capabilities: &str, response: &str) -> PathBuf {
+    let script = format!(
+        "#!/bin/sh\nif [ \"$1\" = \"--capabilities\" ]; then echo '{}'; exit 0; fi\necho \"$@\" > args.txt\ncat > request.json\necho '{}'\n",
+        capabilities, response
+    );
+    let dir = common::dir(name, &[("a.dcg", schema), ("gen.sh", &script)]);
+    std::fs::set_permissions(dir.join("gen.sh"), std::fs::Permissions::from_mode(0o755)).unwrap();
+    dir
+}
+
+fn capabilities(features: &str) -> String {
+    format!("{{\"protocol_version\":{},\"features\":[{}]}}", PROTOCOL_VERSION, features)
+}
+
+fn dcg(dir: &PathBuf, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_dcg"))
+        .current_dir(dir)
+        .args(["a.dcg", "-g", "./gen.sh", "-o", "out"])
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+const SCHEMA: &str = "namespace app\nstruct User { id: Int32 }\n";
+const RESPONSE: &str = "{\"files\":[{\"path\":\"x/user.txt\",\"contents\":\"hi\"}],\"diagnostics\":[{\"severity\":\"Warning\",\"message\":\"careful\"}]}";
+
+#[test]
+fn exchanges_request_and_response_over_stdio() {
+    let dir = dir("stdio", SCHEMA, &capabilities(""), RESPONSE);
+    let output = dcg(&dir, &[]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("[./gen.sh] Warning: careful"));
+    assert_eq!(std::fs::read_to_string(dir.join("args.txt")).unwrap(), "- out\n");
+    assert_eq!(std::fs::read_to_string(dir.join("out/x/user.txt")).unwrap(), "hi");
+
+    let request = serde_json::from_str::<Request>(&std::fs::read_to_string(dir.join("request.json")).unwrap()).unwrap();
+    assert_eq!(request.ast.files()[0].namespace().to_string(), "app");
+}
+
+#[test]
+fn lists_files_without_writing_them_in_dry_runs() {
+    let dir = dir("dry_run", SCHEMA, &capabilities(""), RESPONSE);
+    let output = dcg(&dir, &["--dry-run"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Would write: \"out/x/user.txt\" (2 bytes)"));
+    assert!(!dir.join("out").exists());
+}
+
+#[test]
+fn refuses_paths_outside_of_the_output_directory() {
+    let response = "{\"files\":[{\"path\":\"ok.txt\",\"contents\":\"\"},{\"path\":\"../escaped.txt\",\"contents\":\"\"}]}";
+    let dir = dir("escape", SCHEMA, &capabilities(""), response);
+    let output = dcg(&dir, &[]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Refusing to write outside of the output directory: \"../escaped.txt\""));
+    assert!(!dir.join("out/ok.txt").exists());
+    assert!(!dir.join("escaped.txt").exists());
+}
+
+#[test]
+fn sends_versions_in_the_request() {
+    let dir = dir("versions", SCHEMA, &capabilities(""), RESPONSE);
+    assert_eq!(dcg(&dir, &[]).status.code(), Some(0));
+    let request = serde_json::from_str::<Request>(&std::fs::read_to_string(dir.join("request.json")).unwrap()).unwrap();
+    assert_eq!(request.protocol_version, PROTOCOL_VERSION);
+    assert_eq!(request.dcg_version, env!("CARGO_PKG_VERSION"));
+}
+
+#[test]
+fn rejects_other_protocol_versions() {
+    let capabilities = format!("{{\"protocol_version\":{},\"features\":[]}}", PROTOCOL_VERSION + 1);
+    let dir = dir("other_version", SCHEMA, &capabilities, RESPONSE);
+    let output = dcg(&dir, &[]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains(&format!(
+        "./gen.sh uses protocol version {} but dcg uses version {}.", PROTOCOL_VERSION + 1, PROTOCOL_VERSION
+    )));
+    assert!(!dir.join("request.json").exists());
+}
+
+#[test]
+fn rejects_generators_missing_features() {
+    let schema = "namespace app\nstruct User { id: Option<Int32>\n tags: Map<String String> }\n";
+    let dir = dir("missing_features", schema, &capabilities("\"Map\""), RESPONSE);
+    let output = dcg(&dir, &[]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("./gen.sh does not support features used by the input: [Option]"));
+    assert!(!dir.join("request.json").exists());
+}
+
+#[test]
+fn ignores_unknown_features() {
+    let dir = dir("unknown_features", SCHEMA, &capabilities("\"Teleportation\""), RESPONSE);
+    assert_eq!(dcg(&dir, &[]).status.code(), Some(0));
+}
+
+#[test]
+fn sends_options_in_the_request() {
+    let dir = dir("options", SCHEMA, &capabilities(""), RESPONSE);
+    let output = dcg(&dir, &["--opt", "./gen.sh:derive=Clone,Debug", "--opt", "./gen.sh:std=17", "--opt", "rust:derive=Debug", "-g", "rust"]);
+    assert_eq!(output.status.code(), Some(0));
+    let request = serde_json::from_str::<Request>(&std::fs::read_to_string(dir.join("request.json")).unwrap()).unwrap();
+    let options = request.options.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect::<Vec<(&str, &str)>>();
+    assert_eq!(options, vec![("derive", "Clone,Debug"), ("std", "17")]);
+}
+
+#[test]
+fn sends_annotations_in_the_request() {
+    let schema = "namespace app\n@table(name=\"users\")\nstruct User { id: Int32 }\n";
+    let dir = dir("annotations", schema, &capabilities(""), RESPONSE);
+    assert_eq!(dcg(&dir, &[]).status.code(), Some(0));
+    let request = serde_json::from_str::<Request>(&std::fs::read_to_string(dir.join("request.json")).unwrap()).unwrap();
+    let annotation = request.ast.files()[0].types()[0].annotation("table").unwrap();
+    assert_eq!(annotation.string_arg("name"), Some("users"));
+}
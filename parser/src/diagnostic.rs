@@ -0,0 +1,67 @@
+use std::{fmt::Display, path::{Path, PathBuf}};
+
+use pest::error::{Error, ErrorVariant, LineColLocation};
+
+use crate::Rule;
+
+/// A 1-based line and column in a source file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LineCol {
+    pub line: usize,
+    pub column: usize
+}
+
+/// An error found while parsing a single input file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseDiagnostic {
+    pub path: PathBuf,
+    pub start: LineCol,
+    pub end: Option<LineCol>,
+    pub message: String
+}
+
+/// Names a rule in an error. Rules that only match a single token are named after that token.
+fn rule_name(rule: &Rule) -> String {
+    match rule {
+        Rule::optional => "`?`".to_string(),
+        Rule::colon => "`:`".to_string(),
+        rule => format!("{:?}", rule)
+    }
+}
+
+fn rule_list(rules: &[Rule]) -> String {
+    rules.iter().map(rule_name).collect::<Vec<String>>().join(", ")
+}
+
+impl ParseDiagnostic {
+    pub(crate) fn from_pest(path: &Path, e: Error<Rule>) -> ParseDiagnostic {
+        let (start, end) = match e.line_col {
+            LineColLocation::Pos((line, column)) => (LineCol { line, column }, None),
+            LineColLocation::Span((line, column), (end_line, end_column)) =>
+                (LineCol { line, column }, Some(LineCol { line: end_line, column: end_column }))
+        };
+
+        let message = match e.variant {
+            ErrorVariant::ParsingError { positives, negatives } => match (positives.is_empty(), negatives.is_empty()) {
+                (false, false) => format!("unexpected {}; expected {}", rule_list(&negatives), rule_list(&positives)),
+                (false, true) => format!("expected {}", rule_list(&positives)),
+                (true, false) => format!("unexpected {}", rule_list(&negatives)),
+                (true, true) => "unknown parsing error".to_string()
+            },
+            ErrorVariant::CustomError { message } => message
+        };
+
+        ParseDiagnostic {
+            path: path.to_path_buf(),
+            start,
+            end,
+            message
+        }
+    }
+}
+
+impl Display for ParseDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}: {}", self.path.display(), self.start.line, self.start.column, self.message)
+    }
+}
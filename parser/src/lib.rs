@@ -1,24 +1,50 @@
 extern crate pest;
 #[macro_use] extern crate pest_derive;
 
+mod diagnostic;
 mod raw_to_ast;
 
+use std::path::{Path, PathBuf};
+
 use pest::Parser;
 
+pub use diagnostic::LineCol;
+pub use diagnostic::ParseDiagnostic;
+
 #[derive(Parser)]
 #[grammar = "dataclass.pest"]
 pub struct RawParser;
 
-pub fn parse(file_contents: &Vec<String>) -> ast::Root {
-    let parsed_files = file_contents.iter()
-        .map(
-            |input| RawParser::parse(Rule::file, input)
-                .unwrap_or_else(|e| panic!("{}", e))
-                .next()
-                .unwrap()
-        )
-        .map(raw_to_ast::convert_file)
-        .collect();
-
-    ast::Root::new(parsed_files)
-}
\ No newline at end of file
+/// Parses a single file. `index` is the position the file will have in `Root::files` and is
+/// recorded in every span.
+pub fn parse_file(path: &Path, input: &str, index: usize) -> Result<ast::File, ParseDiagnostic> {
+    RawParser::parse(Rule::file, input)
+        .map_err(Box::new)
+        .and_then(|mut pairs| match pairs.next() {
+            Some(pair) => raw_to_ast::convert_file(pair, index, path),
+            None => Err(Box::new(pest::error::Error::new_from_pos(
+                pest::error::ErrorVariant::CustomError { message: "empty parse tree".to_string() },
+                pest::Position::from_start(input)
+            )))
+        })
+        .map_err(|e| ParseDiagnostic::from_pest(path, *e))
+}
+
+/// Parses every `(path, contents)` pair, collecting the diagnostics from all files that fail.
+pub fn parse(files: &[(PathBuf, String)]) -> Result<ast::Root, Vec<ParseDiagnostic>> {
+    let mut parsed_files = vec![];
+    let mut diagnostics = vec![];
+
+    for (index, (path, input)) in files.iter().enumerate() {
+        match parse_file(path, input, index) {
+            Ok(file) => parsed_files.push(file),
+            Err(d) => diagnostics.push(d)
+        }
+    }
+
+    if !diagnostics.is_empty() {
+        return Err(diagnostics);
+    }
+
+    Ok(ast::Root::new(parsed_files, ast::Context::new()))
+}
@@ -1,12 +1,36 @@
-use pest::iterators::Pair;
+use std::{collections::BTreeMap, path::{Path, PathBuf}};
+
+use pest::error::{Error, ErrorVariant};
+use pest::iterators::{Pair, Pairs};
 use crate::Rule;
 use ast::*;
 
+pub(crate) type ConvertResult<T> = Result<T, Box<Error<Rule>>>;
+
+fn error<T>(pair: &Pair<Rule>, message: String) -> ConvertResult<T> {
+    Err(Box::new(Error::new_from_span(ErrorVariant::CustomError { message }, pair.as_span())))
+}
+
+/// Returns the next child of `parent`, or an error pointing at `parent` if the tree is missing one.
+fn next<'a>(pairs: &mut Pairs<'a, Rule>, parent: &Pair<'a, Rule>, expected: Rule) -> ConvertResult<Pair<'a, Rule>> {
+    match pairs.next() {
+        Some(pair) => Ok(pair),
+        None => error(parent, format!("expected {:?} in {:?}", expected, parent.as_rule()))
+    }
+}
+
+fn convert_span(pair: &Pair<Rule>, file: usize) -> Span {
+    let span = pair.as_span();
+    let (line, column) = span.start_pos().line_col();
+    Span::new(file, span.start(), span.end(), line, column)
+}
+
 fn convert_identifier(ident: Pair<Rule>) -> Name {
     Name::from(ident.as_str().trim())
 }
 
-fn convert_qualified_name(pair: Pair<Rule>) -> QualifiedName {
+fn convert_qualified_name(pair: Pair<Rule>, file: usize) -> QualifiedName {
+    let span = convert_span(&pair, file);
     let pairs = pair.into_inner();
     let mut identifiers = vec![];
 
@@ -14,89 +38,352 @@ fn convert_qualified_name(pair: Pair<Rule>) -> QualifiedName {
         identifiers.push(convert_identifier(ident));
     }
 
-    QualifiedName::new(identifiers)
+    let mut name = QualifiedName::new(identifiers);
+    name.set_span(Some(span));
+    name
+}
+
+fn convert_namespace(pair: Pair<Rule>, file: usize) -> ConvertResult<QualifiedName> {
+    let mut pairs = pair.clone().into_inner();
+    Ok(convert_qualified_name(next(&mut pairs, &pair, Rule::qualified_name)?, file))
+}
+
+fn convert_import(pair: Pair<Rule>, file: usize) -> ConvertResult<Import> {
+    let mut pairs = pair.clone().into_inner();
+    let inner = next(&mut pairs, &pair, Rule::qualified_name)?;
+    match inner.as_rule() {
+        Rule::qualified_name => Ok(Import::Namespace(convert_qualified_name(inner, file))),
+        Rule::string => Ok(Import::Path(PathBuf::from(convert_string(inner)?))),
+        rule => error(&inner, format!("unexpected {:?} in import", rule))
+    }
+}
+
+/// Returns the type parameters and the length that may follow them.
+fn convert_type_params(pair: Pair<Rule>, file: usize) -> ConvertResult<(Vec<TypeRef>, Option<u64>)> {
+    let mut params = vec![];
+    let mut length = None;
+    for pair in pair.into_inner() {
+        match pair.as_rule() {
+            Rule::length => match pair.as_str().parse::<u64>() {
+                Ok(n) => length = Some(n),
+                Err(e) => return error(&pair, format!("invalid length: {}", e))
+            },
+            _ => params.push(convert_type_ref(pair, file)?)
+        }
+    }
+    Ok((params, length))
+}
+
+fn convert_type_ref(pair: Pair<Rule>, file: usize) -> ConvertResult<TypeRef> {
+    let span = convert_span(&pair, file);
+    let mut pairs = pair.clone().into_inner();
+    let name = convert_qualified_name(next(&mut pairs, &pair, Rule::qualified_name)?, file);
+    let (params, length) = match pairs.next() {
+        Some(params) => convert_type_params(params, file)?,
+        None => (vec![], None)
+    };
+
+    Ok(TypeRef::new(
+        name,
+        params,
+        length,
+        None,
+        Some(span)
+    ))
+}
+
+fn convert_string(pair: Pair<Rule>) -> ConvertResult<String> {
+    let mut pairs = pair.clone().into_inner();
+    Ok(unescape(next(&mut pairs, &pair, Rule::string_contents)?.as_str()))
+}
+
+/// Replaces the escapes in a string's contents. The grammar only allows the ones handled here.
+fn unescape(contents: &str) -> String {
+    let mut out = String::with_capacity(contents.len());
+    let mut chars = contents.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => match chars.next() {
+                Some('n') => out.push('\n'),
+                Some('r') => out.push('\r'),
+                Some('t') => out.push('\t'),
+                Some(ch) => out.push(ch),
+                None => {}
+            },
+            ch => out.push(ch)
+        }
+    }
+    out
+}
+
+fn convert_literal(pair: Pair<Rule>, file: usize) -> ConvertResult<Literal> {
+    let mut pairs = pair.clone().into_inner();
+    let inner = next(&mut pairs, &pair, Rule::literal)?;
+    match inner.as_rule() {
+        Rule::bool_literal => Ok(Literal::Bool(inner.as_str() == "true")),
+        Rule::int_literal => match inner.as_str().parse::<i64>() {
+            Ok(i) => Ok(Literal::Int(i)),
+            Err(e) => error(&inner, format!("invalid integer: {}", e))
+        },
+        Rule::float_literal => match inner.as_str().parse::<f64>() {
+            Ok(x) if x.is_finite() => Ok(Literal::Float(x)),
+            _ => error(&inner, "float out of range".to_string())
+        },
+        Rule::string => Ok(Literal::String(convert_string(inner)?)),
+        Rule::list_literal => inner.into_inner()
+            .map(|p| convert_literal(p, file))
+            .collect::<ConvertResult<Vec<Literal>>>()
+            .map(Literal::List),
+        Rule::map_literal => inner.into_inner()
+            .map(|entry| {
+                let mut pairs = entry.clone().into_inner();
+                let key = convert_literal(next(&mut pairs, &entry, Rule::literal)?, file)?;
+                let value = convert_literal(next(&mut pairs, &entry, Rule::literal)?, file)?;
+                Ok((key, value))
+            })
+            .collect::<ConvertResult<Vec<(Literal, Literal)>>>()
+            .map(Literal::Map),
+        Rule::qualified_name => Ok(Literal::Variant(convert_qualified_name(inner, file))),
+        rule => error(&inner, format!("unexpected {:?} in literal", rule))
+    }
 }
 
-fn convert_namespace(pair: Pair<Rule>) -> QualifiedName {
-    convert_qualified_name(pair.into_inner().next().unwrap())
+fn convert_annotation(pair: Pair<Rule>, file: usize) -> ConvertResult<Annotation> {
+    let span = convert_span(&pair, file);
+    let mut pairs = pair.clone().into_inner();
+    let name = next(&mut pairs, &pair, Rule::ident)?.as_str().to_string();
+
+    let mut args = BTreeMap::new();
+    for arg in pairs.flat_map(Pair::into_inner) {
+        let mut pairs = arg.clone().into_inner();
+        let key = next(&mut pairs, &arg, Rule::ident)?;
+        let value = convert_literal(next(&mut pairs, &arg, Rule::literal)?, file)?;
+        if args.insert(key.as_str().to_string(), value).is_some() {
+            return error(&key, format!("duplicate argument \"{}\" for @{}", key.as_str(), name));
+        }
+    }
+
+    Ok(Annotation::new(name, args, Some(span)))
 }
 
-fn convert_type_params(pair: Pair<Rule>) -> Vec<Box<TypeRef>> {
-    pair.into_inner()
-        .map(convert_type_ref)
-        .map(Box::new)
-        .collect()
+/// Returns the text of a `///` comment. Like rustdoc, one space after the `///` is dropped.
+fn convert_doc_comment(pair: Pair<Rule>) -> String {
+    let text = pair.into_inner().next().map(|p| p.as_str()).unwrap_or_default();
+    text.strip_prefix(' ').unwrap_or(text).trim_end().to_string()
 }
 
-fn convert_type_ref(pair: Pair<Rule>) -> TypeRef {
-    let mut pairs = pair.into_inner();
+/// Converts the doc comments and annotations at the start of `pairs`, which may be interleaved.
+fn convert_attributes(pairs: &mut Pairs<Rule>, file: usize) -> ConvertResult<(Vec<String>, Vec<Annotation>)> {
+    let mut doc = vec![];
+    let mut annotations = vec![];
+    while let Some(pair) = pairs.peek() {
+        match pair.as_rule() {
+            Rule::doc_comment => doc.push(convert_doc_comment(pair)),
+            Rule::annotation => annotations.push(convert_annotation(pair, file)?),
+            _ => break
+        }
+        pairs.next();
+    }
+    Ok((doc, annotations))
+}
 
-    TypeRef::new(
-        convert_qualified_name(pairs.next().unwrap()),
-        pairs.next().map(convert_type_params).unwrap_or(vec![])
-    )
+fn convert_field(pair: Pair<Rule>, file: usize) -> ConvertResult<Field> {
+    let span = convert_span(&pair, file);
+    let mut pairs = pair.clone().into_inner();
+    let (doc, annotations) = convert_attributes(&mut pairs, file)?;
+    let name = convert_identifier(next(&mut pairs, &pair, Rule::ident)?);
+    let optional = match pairs.peek() {
+        Some(pair) if pair.as_rule() == Rule::optional => pairs.next().is_some(),
+        _ => false
+    };
+    next(&mut pairs, &pair, Rule::colon)?;
+    let type_ref = convert_type_ref(next(&mut pairs, &pair, Rule::type_ref)?, file)?;
+    let default = match pairs.next() {
+        Some(literal) => Some(convert_literal(literal, file)?),
+        None => None
+    };
+    Ok(Field::new(
+        name,
+        optional,
+        type_ref,
+        default,
+        doc,
+        annotations,
+        Some(span)
+    ))
+}
+
+fn convert_generic_params(pairs: &mut Pairs<Rule>) -> Vec<Name> {
+    match pairs.peek() {
+        Some(pair) if pair.as_rule() == Rule::generic_params => pairs.next()
+            .into_iter()
+            .flat_map(Pair::into_inner)
+            .map(convert_identifier)
+            .collect(),
+        _ => vec![]
+    }
 }
 
-fn convert_field(pair: Pair<Rule>) -> Field {
-    let mut pairs = pair.into_inner();
-    let name = convert_identifier(pairs.next().unwrap());
-    let type_ref = convert_type_ref(pairs.next().unwrap());
-    Field::new(
+fn convert_enum(pair: Pair<Rule>, file: usize, doc: Vec<String>, annotations: Vec<Annotation>) -> ConvertResult<TypeDef> {
+    let span = convert_span(&pair, file);
+    let mut pairs = pair.clone().into_inner();
+    let name = convert_identifier(next(&mut pairs, &pair, Rule::ident)?);
+    let params = convert_generic_params(&mut pairs);
+    let fields = pairs
+        .filter(|p| p.as_rule() == Rule::field)
+        .map(|p| convert_field(p, file))
+        .collect::<ConvertResult<Vec<Field>>>()?;
+
+    Ok(TypeDef::new(
+        name,
+        params,
+        doc,
+        annotations,
+        Type::Enum(Enum::new(fields)),
+        Some(span)
+    ))
+}
+
+fn convert_enum_value(pair: Pair<Rule>, file: usize) -> ConvertResult<EnumValue> {
+    let span = convert_span(&pair, file);
+    let mut pairs = pair.clone().into_inner();
+    let (doc, annotations) = convert_attributes(&mut pairs, file)?;
+    let name = convert_identifier(next(&mut pairs, &pair, Rule::ident)?);
+    let discriminant = match pairs.next() {
+        Some(int) => match int.as_str().parse::<i64>() {
+            Ok(i) => Some(i),
+            Err(e) => return error(&int, format!("invalid integer: {}", e))
+        },
+        None => None
+    };
+
+    Ok(EnumValue::new(
+        name,
+        discriminant,
+        doc,
+        annotations,
+        Some(span)
+    ))
+}
+
+fn convert_c_enum(pair: Pair<Rule>, file: usize, doc: Vec<String>, annotations: Vec<Annotation>) -> ConvertResult<TypeDef> {
+    let span = convert_span(&pair, file);
+    let mut pairs = pair.clone().into_inner();
+    let name = convert_identifier(next(&mut pairs, &pair, Rule::ident)?);
+    let backing = match pairs.peek() {
+        Some(pair) if pair.as_rule() == Rule::type_ref => Some(convert_type_ref(pair, file)?),
+        _ => None
+    };
+    let values = pairs
+        .filter(|p| p.as_rule() == Rule::enum_value)
+        .map(|p| convert_enum_value(p, file))
+        .collect::<ConvertResult<Vec<EnumValue>>>()?;
+
+    Ok(TypeDef::new(
         name,
-        type_ref
-    )
+        vec![],
+        doc,
+        annotations,
+        Type::CEnum(CEnum::new(backing, values)),
+        Some(span)
+    ))
 }
 
-fn convert_enum(pair: Pair<Rule>) -> TypeDef {
-    let mut pairs = pair.into_inner();
-    let name = convert_identifier(pairs.next().unwrap());
-    let fields = pairs.into_iter()
-        .map(convert_field)
-        .collect();
+/// Converts an `alias_def` or a `newtype_def`, which only differ in the `Type` they make.
+fn convert_wrapper(pair: Pair<Rule>, file: usize, doc: Vec<String>, annotations: Vec<Annotation>, type_: fn(TypeRef) -> Type) -> ConvertResult<TypeDef> {
+    let span = convert_span(&pair, file);
+    let mut pairs = pair.clone().into_inner();
+    let name = convert_identifier(next(&mut pairs, &pair, Rule::ident)?);
+    let target = convert_type_ref(next(&mut pairs, &pair, Rule::type_ref)?, file)?;
 
-    TypeDef::new(
+    Ok(TypeDef::new(
         name,
-        Type::Enum(Enum::new(fields))
-    )
+        vec![],
+        doc,
+        annotations,
+        type_(target),
+        Some(span)
+    ))
 }
 
-fn convert_struct(pair: Pair<Rule>) -> TypeDef {
-    let mut pairs = pair.into_inner();
-    let name = convert_identifier(pairs.next().unwrap());
-    let fields = pairs.into_iter()
-        .map(convert_field)
-        .collect();
+fn convert_struct(pair: Pair<Rule>, file: usize, doc: Vec<String>, annotations: Vec<Annotation>) -> ConvertResult<TypeDef> {
+    let span = convert_span(&pair, file);
+    let mut pairs = pair.clone().into_inner();
+    let name = convert_identifier(next(&mut pairs, &pair, Rule::ident)?);
+    let params = convert_generic_params(&mut pairs);
+    let fields = pairs
+        .filter(|p| p.as_rule() == Rule::field)
+        .map(|p| convert_field(p, file))
+        .collect::<ConvertResult<Vec<Field>>>()?;
 
-    TypeDef::new(
+    Ok(TypeDef::new(
         name,
-        Type::Struct(Struct::new(fields))
-    )
+        params,
+        doc,
+        annotations,
+        Type::Struct(Struct::new(fields)),
+        Some(span)
+    ))
 }
 
-fn convert_type_def(pair: Pair<Rule>) -> TypeDef {
-    let tokens = pair.clone().tokens();
-    let pair = pair.into_inner().next().expect(format!("{:?}", tokens).as_str());
-    match pair.as_rule() {
-        Rule::struct_def => convert_struct(pair),
-        Rule::enum_def => convert_enum(pair),
-        _ => panic!()
+fn convert_const_def(pair: Pair<Rule>, file: usize) -> ConvertResult<ConstDef> {
+    let span = convert_span(&pair, file);
+    let mut pairs = pair.clone().into_inner();
+    let (doc, annotations) = convert_attributes(&mut pairs, file)?;
+    let name = convert_identifier(next(&mut pairs, &pair, Rule::ident)?);
+    let type_ref = convert_type_ref(next(&mut pairs, &pair, Rule::type_ref)?, file)?;
+    let value = convert_literal(next(&mut pairs, &pair, Rule::literal)?, file)?;
+
+    Ok(ConstDef::new(
+        name,
+        type_ref,
+        value,
+        doc,
+        annotations,
+        Some(span)
+    ))
+}
+
+fn convert_type_def(pair: Pair<Rule>, file: usize) -> ConvertResult<TypeDef> {
+    let mut pairs = pair.clone().into_inner();
+    let (doc, annotations) = convert_attributes(&mut pairs, file)?;
+    let inner = next(&mut pairs, &pair, Rule::type_def)?;
+    match inner.as_rule() {
+        Rule::struct_def => convert_struct(inner, file, doc, annotations),
+        Rule::enum_def => convert_enum(inner, file, doc, annotations),
+        Rule::c_enum_def => convert_c_enum(inner, file, doc, annotations),
+        Rule::alias_def => convert_wrapper(inner, file, doc, annotations, Type::Alias),
+        Rule::newtype_def => convert_wrapper(inner, file, doc, annotations, Type::Newtype),
+        rule => error(&inner, format!("unexpected {:?} in type definition", rule))
     }
 }
 
-pub(crate) fn convert_file(pair: Pair<Rule>) -> File {
-    let mut pairs = pair.into_inner();
-    let namespace = convert_namespace(pairs.next().unwrap());
+/// Converts a parsed file. `file` is the index of the file in `Root::files` and is recorded in every `Span`.
+pub(crate) fn convert_file(pair: Pair<Rule>, file: usize, path: &Path) -> ConvertResult<File> {
+    let mut pairs = pair.clone().into_inner().skip_while(|p| p.as_rule() == Rule::doc_comment);
+    let namespace = match pairs.next() {
+        Some(namespace) => convert_namespace(namespace, file)?,
+        None => return error(&pair, "expected namespace in file".to_string())
+    };
 
+    let mut imports = vec![];
+    let mut consts = vec![];
     let mut types = vec![];
-    for pair in pairs.into_iter() {
+    for pair in pairs {
         match pair.as_rule() {
-            Rule::type_def => types.push(convert_type_def(pair)),
+            Rule::import => imports.push(convert_import(pair, file)?),
+            Rule::const_def => consts.push(convert_const_def(pair, file)?),
+            Rule::type_def => types.push(convert_type_def(pair, file)?),
             _ => {}
         }
     }
 
-    File::new(
+    Ok(File::new(
+        Some(path.to_path_buf()),
+        false,
         namespace,
+        imports,
+        consts,
         types
-    )
-}
\ No newline at end of file
+    ))
+}
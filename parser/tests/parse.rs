@@ -0,0 +1,169 @@
+use std::path::{Path, PathBuf};
+
+use ast::*;
+use parser::LineCol;
+
+fn parse(input: &str) -> File {
+    parser::parse_file(Path::new("test.dcg"), input, 0).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Returns the message of the error `input` fails with.
+fn parse_error(input: &str) -> String {
+    match parser::parse_file(Path::new("test.dcg"), input, 0) {
+        Ok(_) => panic!("{:?} parsed", input),
+        Err(e) => e.message
+    }
+}
+
+fn fields(file: &File) -> &[Field] {
+    match file.types()[0].type_() {
+        Type::Struct(s) => s.fields(),
+        type_ => panic!("not a struct: {:?}", type_)
+    }
+}
+
+#[test]
+fn parses_optional_fields() {
+    let file = parse("namespace a\nstruct S { x?: Int32\n y: Int32 }");
+    let optional = fields(&file).iter().map(|f| *f.optional()).collect::<Vec<bool>>();
+    assert_eq!(optional, vec![true, false]);
+}
+
+#[test]
+fn reports_missing_colon() {
+    assert_eq!(parse_error("namespace a\nstruct S { x: Int32\n y Int32 }"), "expected `?`, `:`");
+}
+
+#[test]
+fn ignores_doc_comments_without_an_item() {
+    let file = parse("/// Header\nnamespace a\n/// Nothing\nstruct S {\n /// Field\n x: Int32\n /// Nothing\n}\nenum C { A\n /// Nothing\n}\n/// Nothing\n");
+    assert_eq!(file.types().len(), 2);
+    assert_eq!(fields(&file).len(), 1);
+    assert_eq!(fields(&file)[0].doc(), &vec!["Field".to_string()]);
+    assert_eq!(file.types()[0].doc(), &vec!["Nothing".to_string()]);
+}
+
+#[test]
+fn collects_errors_from_every_file() {
+    let files = vec![
+        (PathBuf::from("a.dcg"), "namespace a\nstruct S {".to_string()),
+        (PathBuf::from("b.dcg"), "namespace b\n".to_string()),
+        (PathBuf::from("c.dcg"), "struct S {}".to_string())
+    ];
+    let diagnostics = parser::parse(&files).unwrap_err();
+    let locations = diagnostics.iter()
+        .map(|d| (d.path.clone(), d.start))
+        .collect::<Vec<(PathBuf, LineCol)>>();
+    assert_eq!(locations, vec![
+        (PathBuf::from("a.dcg"), LineCol { line: 2, column: 11 }),
+        (PathBuf::from("c.dcg"), LineCol { line: 1, column: 1 })
+    ]);
+    assert_eq!(diagnostics[1].to_string(), "c.dcg:1:1: expected namespace");
+}
+
+#[test]
+fn records_spans() {
+    let input = "namespace a\n\nstruct S {\n  x: Foo\n}\n";
+    let file = parse(input);
+    let type_def = &file.types()[0];
+    let field = &fields(&file)[0];
+    let span = type_def.span().unwrap();
+    assert_eq!((span.line(), span.column()), (3, 1));
+    assert_eq!(&input[span.offset()..span.end()], "struct S {\n  x: Foo\n}");
+    let span = field.span().unwrap();
+    assert_eq!((span.line(), span.column()), (4, 3));
+    let span = field.type_().span().unwrap();
+    assert_eq!((span.line(), span.column()), (4, 6));
+
+    let errors = ast::resolve(Root::new(vec![file.clone()], Context::new())).unwrap_err();
+    assert_eq!(errors[0].to_string(), "test.dcg:4:6: Unknown type \"Foo\" in a.S");
+}
+
+#[test]
+fn parses_generic_params() {
+    let file = parse("namespace a\nstruct Page<T> { items: List<T> }\nenum Either<L R> { Left: L\n Right: R }");
+    let params = file.types().iter().map(|t| t.params().len()).collect::<Vec<usize>>();
+    assert_eq!(params, vec![1, 2]);
+    assert_eq!(fields(&file)[0].type_().params()[0].name(), &QualifiedName::from(vec!["T"]));
+}
+
+#[test]
+fn parses_default_values() {
+    let file = parse("namespace a\nstruct S { a: Int32 = -5\n b: Float64 = 1.5\n c: String = \"x\"\n d: List<Bool> = [true, false]\n e: Map<String Int32> = {\"k\": 1}\n f: Color = Color.Red\n g: Int32 }");
+    let defaults = fields(&file).iter().map(|f| f.default().clone()).collect::<Vec<Option<Literal>>>();
+    assert_eq!(defaults, vec![
+        Some(Literal::Int(-5)),
+        Some(Literal::Float(1.5)),
+        Some(Literal::String("x".to_string())),
+        Some(Literal::List(vec![Literal::Bool(true), Literal::Bool(false)])),
+        Some(Literal::Map(vec![(Literal::String("k".to_string()), Literal::Int(1))])),
+        Some(Literal::Variant(QualifiedName::from(vec!["Color", "Red"]))),
+        None
+    ]);
+}
+
+#[test]
+fn parses_string_escapes() {
+    let file = parse("namespace a\nimport \"dir\\\\b.dcg\"\nstruct S { a: String = \"say \\\"hi\\\"\\\\\\n\\t\" }");
+    assert_eq!(file.imports(), &vec![Import::Path(PathBuf::from("dir\\b.dcg"))]);
+    let default = fields(&file)[0].default().clone().unwrap();
+    assert_eq!(default, Literal::String("say \"hi\"\\\n\t".to_string()));
+    assert_eq!(default.to_string(), "\"say \\\"hi\\\"\\\\\\n\\t\"");
+    assert!(parse_error("namespace a\nstruct S { a: String = \"\\q\" }").starts_with("expected"));
+}
+
+#[test]
+fn parses_annotations() {
+    let file = parse("namespace a\n@rust(derive=\"Hash\", order=2)\n@deprecated\nstruct S {\n @json(name=\"user_id\")\n id: Int32\n}");
+    let type_def = &file.types()[0];
+    let names = type_def.annotations().iter().map(|a| a.name().as_str()).collect::<Vec<&str>>();
+    assert_eq!(names, vec!["rust", "deprecated"]);
+    let rust = type_def.annotation("rust").unwrap();
+    assert_eq!(rust.string_arg("derive"), Some("Hash"));
+    assert_eq!(rust.arg("order"), Some(&Literal::Int(2)));
+    assert_eq!(fields(&file)[0].annotation("json").and_then(|a| a.string_arg("name")), Some("user_id"));
+}
+
+#[test]
+fn parses_doc_comments() {
+    let file = parse("namespace a\n//// Not a doc comment\n/// A user.\n///\n/// Second paragraph.\nstruct S {\n /// The id.\n // Not a doc comment either\n id: Int32\n}");
+    assert_eq!(file.types()[0].doc(), &vec!["A user.".to_string(), "".to_string(), "Second paragraph.".to_string()]);
+    assert_eq!(fields(&file)[0].doc(), &vec!["The id.".to_string()]);
+}
+
+#[test]
+fn parses_constants() {
+    let file = parse("namespace a\n/// Max.\nconst MAX: UInt8 = 10\nconst NAME: String = \"n\"\nstruct S {}\n");
+    let consts = file.consts().iter().map(|c| (c.name().to_string(), c.value().clone())).collect::<Vec<(String, Literal)>>();
+    assert_eq!(consts, vec![("MAX".to_string(), Literal::Int(10)), ("NAME".to_string(), Literal::String("n".to_string()))]);
+    assert_eq!(file.consts()[0].doc(), &vec!["Max.".to_string()]);
+    assert_eq!(file.types().len(), 1);
+}
+
+#[test]
+fn parses_c_enums() {
+    let file = parse("namespace a\nenum Flags : UInt16 { A = 1, B\n C = -10 }\nenum Plain { X, Y }\nenum Tagged { X: Int32 }\n");
+    let Type::CEnum(flags) = file.types()[0].type_() else {
+        panic!("not a C-like enum");
+    };
+    assert_eq!(flags.backing().as_ref().map(|b| b.name().to_string()), Some("UInt16".to_string()));
+    let values = flags.values().iter().map(|v| (v.name().to_string(), *v.discriminant())).collect::<Vec<(String, Option<i64>)>>();
+    assert_eq!(values, vec![("A".to_string(), Some(1)), ("B".to_string(), None), ("C".to_string(), Some(-10))]);
+    assert!(matches!(file.types()[1].type_(), Type::CEnum(e) if e.backing().is_none()));
+    assert!(matches!(file.types()[2].type_(), Type::Enum(_)));
+}
+
+#[test]
+fn parses_aliases_and_newtypes() {
+    let file = parse("namespace a\ntype Tags = List<String>\nnewtype UserId = Int64\n");
+    assert!(matches!(file.types()[0].type_(), Type::Alias(t) if t.name().to_string() == "List" && t.params().len() == 1));
+    assert!(matches!(file.types()[1].type_(), Type::Newtype(t) if t.name().to_string() == "Int64"));
+}
+
+#[test]
+fn parses_array_lengths() {
+    let file = parse("namespace a\nstruct S { hash: Array<UInt8 32>\n grid: Array<Array<Int32 3> 2>\n data: Bytes }");
+    let lengths = fields(&file).iter().map(|f| *f.type_().length()).collect::<Vec<Option<u64>>>();
+    assert_eq!(lengths, vec![Some(32), Some(2), None]);
+    assert_eq!(*fields(&file)[1].type_().params()[0].length(), Some(3));
+}